@@ -3,35 +3,88 @@ use std::cell::RefCell;
 use crate::Result;
 
 use winit::{
-    event::{Event, MouseScrollDelta, WindowEvent},
+    event::{
+        DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, VirtualKeyCode,
+        WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+    window::{CursorGrabMode, Window},
 };
 
+use crate::bookmark::Bookmark;
+use crate::camera::{OrbitMode, View};
+use crate::controls::{ControlSettings, Controls, DragMode};
+use crate::depth::DepthMode;
+use crate::scene::ActiveCamera;
+use crate::viewport::Layout;
 use crate::Renderer;
-use crate::Resources;
+use crate::{LoadOptions, Resources};
 
 #[derive(Debug)]
 pub enum AppEvent {
-    LoadResourcesRequest { path: String },
-    LoadResourcesResponse(Result<Resources>),
+    LoadResourcesRequest {
+        path: String,
+        options: LoadOptions,
+    },
+    /// Boxed, as `Resources` is much larger than the other events
+    LoadResourcesResponse(Result<Box<Resources>>),
+    /// Switches to a material variant, or back to the default materials
+    SetVariantRequest(Option<String>),
+    /// Switches to a glTF camera by name or index, or back to the orbit camera
+    SetCameraRequest(Option<String>),
+    /// Changes the sensitivity and damping of the mouse controls
+    SetControlsRequest(ControlSettings),
+    /// Switches the orbit camera between turntable and trackball orbiting
+    SetOrbitModeRequest(OrbitMode),
+    /// Replaces the bookmarks, until a model is loaded with its own
+    SetBookmarksRequest(Vec<Bookmark>),
+    /// Saves the current view as the bookmark with the given name
+    SaveBookmarkRequest(String),
+    /// Moves the camera to the bookmark with the given name
+    GoToBookmarkRequest(String),
+    /// Splits the window into a single, quad or stereo view
+    SetLayoutRequest(Layout),
+    /// Changes the distance between the eyes of the stereo view
+    SetEyeSeparationRequest(f32),
 }
 
 thread_local! {
     pub static EVENT_LOOP_PROXY: RefCell<Option<EventLoopProxy<AppEvent>>> = RefCell::new(None);
+    /// Material variants of the loaded model
+    pub static VARIANTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Names of the glTF cameras of the loaded model
+    pub static CAMERAS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    /// Bookmarks of the loaded model
+    pub static BOOKMARKS: RefCell<Vec<Bookmark>> = const { RefCell::new(Vec::new()) };
 }
 
 pub async fn run(
     window: winit::window::Window,
     event_loop: EventLoop<AppEvent>,
     line: bool,
+    depth_mode: DepthMode,
 ) -> Result<()> {
     let size = window.inner_size();
 
-    let mut renderer = Renderer::new(&window, size.width, size.height, line).await?;
+    let mut renderer = Renderer::new(&window, size.width, size.height, line, depth_mode).await?;
 
     #[allow(unused_variables)]
     let proxy = event_loop.create_proxy();
 
+    // Kept across model loads, so that models sharing variant names keep the selection
+    let mut variant: Option<String> = None;
+    // Applied once the model is loaded
+    let mut pending_camera: Option<String> = None;
+    let mut pending_bookmark: Option<String> = None;
+    // Where the bookmarks of the model are kept
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut model_path: Option<std::path::PathBuf> = None;
+
+    let mut modifiers = ModifiersState::empty();
+    let mut controls = Controls::default();
+    // Time of the previous frame while animating, for frame-time-based updates
+    let mut last_frame: Option<instant::Instant> = None;
+
     let event_handler = move |event: Event<AppEvent>,
                               _: &EventLoopWindowTarget<AppEvent>,
                               control_flow: &mut ControlFlow| {
@@ -46,21 +99,83 @@ pub async fn run(
                 // On macOS the window needs to be redrawn manually after resizing
                 window.request_redraw();
             }
-            Event::RedrawRequested(_) => renderer.render(),
+            Event::RedrawRequested(_) => {
+                let now = instant::Instant::now();
+                let delta_time = last_frame.map_or(0.0, |last_frame| {
+                    now.duration_since(last_frame).as_secs_f32()
+                });
+                let flying = renderer.scene.active_camera == ActiveCamera::Fly;
+                if let Some(motion) = controls.update(delta_time, flying) {
+                    renderer.move_camera(&motion);
+                }
+                let animating = renderer.update(delta_time) | controls.is_moving(flying);
+                last_frame = animating.then_some(now);
+
+                renderer.render();
+
+                if animating {
+                    window.request_redraw();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(state),
+                ..
+            } => {
+                modifiers = state;
+                controls.modifiers_changed(state);
+            }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => {
+                controls.release_all();
+                lock_pointer(&window, &mut controls, false);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => {
+                controls.mouse_input(button, state);
+                // Looking around with the fly camera locks the pointer, so
+                // that it can turn indefinitely
+                let look = renderer.scene.active_camera == ActiveCamera::Fly
+                    && controls.dragging() == Some(DragMode::Orbit);
+                if look != controls.is_pointer_locked() {
+                    lock_pointer(&window, &mut controls, look);
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                controls.mouse_motion(delta);
+                if controls.is_pointer_locked() {
+                    window.request_redraw();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                controls.cursor_moved(position);
+                if controls.dragging().is_some() {
+                    window.request_redraw();
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                ..
+            } => controls.cursor_left(),
             Event::WindowEvent {
                 event: WindowEvent::MouseWheel { delta, phase, .. },
                 ..
             } => {
                 tracing::debug!(?delta, ?phase);
-                let (x, y) = match delta {
-                    MouseScrollDelta::LineDelta(x, y) => (x, y),
-                    MouseScrollDelta::PixelDelta(pos) => (pos.x as f32, pos.y as f32),
-                };
-                renderer.rotate_camera(x, y);
+                controls.mouse_wheel(delta);
                 window.request_redraw();
             }
             Event::WindowEvent {
@@ -71,30 +186,343 @@ pub async fn run(
                 renderer.zoom_camera(delta as f32);
                 window.request_redraw();
             }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::V),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // Cycle through the variants, then back to the default materials
+                let variants = renderer.variants();
+                let next = match &variant {
+                    Some(name) => variants
+                        .iter()
+                        .position(|variant| variant == name)
+                        .and_then(|index| variants.get(index + 1)),
+                    None => variants.first(),
+                }
+                .map(|name| name.to_string());
+
+                tracing::info!(?next, "Switching material variant");
+                set_variant(&mut renderer, next.as_deref());
+                variant = next;
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::C),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // Cycle through the glTF cameras, then back to the orbit camera
+                let count = renderer.camera_names().len();
+                let next = match renderer.scene.active_camera {
+                    ActiveCamera::Node(index) => Some(index + 1).filter(|&next| next < count),
+                    _ => Some(0).filter(|_| count > 0),
+                };
+
+                tracing::info!(?next, "Switching camera");
+                renderer.scene.set_camera(next, &renderer.queue);
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                renderer.frame_camera();
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::O | VirtualKeyCode::Numpad5),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                renderer.toggle_projection();
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::Numpad1
+                                        | VirtualKeyCode::Numpad3
+                                        | VirtualKeyCode::Numpad7),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // Like in Blender, Ctrl looks from the opposite side
+                let view = match (key, modifiers.ctrl()) {
+                    (VirtualKeyCode::Numpad1, false) => View::Front,
+                    (VirtualKeyCode::Numpad1, true) => View::Back,
+                    (VirtualKeyCode::Numpad3, false) => View::Right,
+                    (VirtualKeyCode::Numpad3, true) => View::Left,
+                    (VirtualKeyCode::Numpad7, false) => View::Top,
+                    _ => View::Bottom,
+                };
+
+                tracing::info!(?view, "Switching view");
+                renderer.set_view(view);
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::T),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                let orbit_mode = match renderer.scene.camera.orbit_mode {
+                    OrbitMode::Turntable => OrbitMode::Trackball,
+                    OrbitMode::Trackball => OrbitMode::Turntable,
+                };
+
+                tracing::info!(?orbit_mode, "Switching orbit mode");
+                renderer.set_orbit_mode(orbit_mode);
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::L),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                let layout = renderer.scene.layout.next();
+
+                tracing::info!(?layout, "Switching layout");
+                renderer.set_layout(layout);
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Tab),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                renderer.toggle_fly_camera();
+                tracing::info!(camera = ?renderer.scene.active_camera, "Switching camera");
+                window.request_redraw();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode:
+                                    Some(
+                                        key @ (VirtualKeyCode::Key1
+                                        | VirtualKeyCode::Key2
+                                        | VirtualKeyCode::Key3
+                                        | VirtualKeyCode::Key4
+                                        | VirtualKeyCode::Key5
+                                        | VirtualKeyCode::Key6
+                                        | VirtualKeyCode::Key7
+                                        | VirtualKeyCode::Key8
+                                        | VirtualKeyCode::Key9),
+                                    ),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // The number keys are declared in order
+                let number = key as usize - VirtualKeyCode::Key1 as usize + 1;
+                let name = crate::bookmark::slot_name(number);
+
+                if modifiers.ctrl() {
+                    tracing::info!(?name, "Saving bookmark");
+                    renderer.save_bookmark(name);
+                    BOOKMARKS.set(renderer.bookmarks().to_vec());
+                    #[cfg(not(target_arch = "wasm32"))]
+                    write_bookmarks(&renderer, model_path.as_deref());
+                } else if renderer.go_to_bookmark(&name) {
+                    tracing::info!(?name, "Going to bookmark");
+                    window.request_redraw();
+                } else {
+                    tracing::info!("No bookmark {name}, press Ctrl+{name} to save one");
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state,
+                                virtual_keycode: Some(key),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                // Fly keys, held down rather than pressed
+                controls.keyboard_input(key, state);
+                if controls.is_moving(renderer.scene.active_camera == ActiveCamera::Fly) {
+                    window.request_redraw();
+                }
+            }
             Event::UserEvent(event) => {
                 tracing::info!(?event, "received user event");
                 match event {
-                    AppEvent::LoadResourcesRequest { path } => {
+                    AppEvent::LoadResourcesRequest { path, options } => {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            model_path = Some(path.clone().into());
+                        }
                         #[cfg(target_arch = "wasm32")]
-                        wasm_bindgen_futures::spawn_local(async {
-                            let resources = Resources::load_gltf(path).await;
+                        wasm_bindgen_futures::spawn_local(async move {
+                            let resources = Resources::load_gltf_with_options(path, options)
+                                .await
+                                .map(Box::new);
                             let _ =
                                 crate::wasm::send_event(AppEvent::LoadResourcesResponse(resources));
                         });
                         // TODO: move this to a separate thread
                         #[cfg(not(target_arch = "wasm32"))]
                         pollster::block_on(async {
-                            let resources = Resources::load_gltf(path).await;
+                            let resources = Resources::load_gltf_with_options(path, options)
+                                .await
+                                .map(Box::new);
                             let _ = proxy.send_event(AppEvent::LoadResourcesResponse(resources));
                         });
                     }
                     AppEvent::LoadResourcesResponse(Ok(resources)) => {
-                        renderer.load_resources(resources);
+                        VARIANTS.set(resources.variants.clone());
+                        renderer.load_resources(*resources);
+                        CAMERAS.set(renderer.camera_names());
+                        if variant.is_some() {
+                            set_variant(&mut renderer, variant.as_deref());
+                        }
+                        if let Some(camera) = pending_camera.take() {
+                            set_camera(&mut renderer, Some(&camera));
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(path) = &model_path {
+                            match crate::bookmark::load(path) {
+                                Ok(bookmarks) => renderer.set_bookmarks(bookmarks),
+                                Err(error) => tracing::warn!(?error, "Failed to read bookmarks"),
+                            }
+                        }
+                        BOOKMARKS.set(renderer.bookmarks().to_vec());
+                        if let Some(name) = pending_bookmark.take() {
+                            // Nothing to animate from yet
+                            if !renderer.restore_bookmark(&name) {
+                                tracing::warn!(?name, "Model has no such bookmark");
+                            }
+                        }
                         window.request_redraw();
                     }
                     AppEvent::LoadResourcesResponse(Err(err)) => {
                         tracing::error!(?err, "failed to load resources");
                     }
+                    AppEvent::SetVariantRequest(name) => {
+                        variant = name;
+                        // Otherwise applied once a model is loaded
+                        if !renderer.scene.models.is_empty() {
+                            set_variant(&mut renderer, variant.as_deref());
+                            window.request_redraw();
+                        }
+                    }
+                    AppEvent::SetCameraRequest(name) => {
+                        if renderer.scene.models.is_empty() {
+                            pending_camera = name;
+                        } else {
+                            set_camera(&mut renderer, name.as_deref());
+                            window.request_redraw();
+                        }
+                    }
+                    AppEvent::SetControlsRequest(settings) => controls.settings = settings,
+                    AppEvent::SetOrbitModeRequest(orbit_mode) => {
+                        renderer.set_orbit_mode(orbit_mode);
+                        window.request_redraw();
+                    }
+                    AppEvent::SetLayoutRequest(layout) => {
+                        renderer.set_layout(layout);
+                        window.request_redraw();
+                    }
+                    AppEvent::SetEyeSeparationRequest(eye_separation) => {
+                        renderer.set_eye_separation(eye_separation);
+                        window.request_redraw();
+                    }
+                    AppEvent::SetBookmarksRequest(bookmarks) => {
+                        renderer.set_bookmarks(bookmarks);
+                        BOOKMARKS.set(renderer.bookmarks().to_vec());
+                    }
+                    AppEvent::SaveBookmarkRequest(name) => {
+                        renderer.save_bookmark(name);
+                        BOOKMARKS.set(renderer.bookmarks().to_vec());
+                        #[cfg(not(target_arch = "wasm32"))]
+                        write_bookmarks(&renderer, model_path.as_deref());
+                    }
+                    AppEvent::GoToBookmarkRequest(name) => {
+                        if renderer.scene.models.is_empty() {
+                            pending_bookmark = Some(name);
+                        } else if renderer.go_to_bookmark(&name) {
+                            window.request_redraw();
+                        } else {
+                            tracing::warn!(?name, "Model has no such bookmark");
+                        }
+                    }
                 }
             }
             _ => {}
@@ -113,3 +541,49 @@ pub async fn run(
     #[allow(unreachable_code)]
     Ok(())
 }
+
+/// Locks the pointer in place and hides it, or releases it.
+fn lock_pointer(window: &Window, controls: &mut Controls, locked: bool) {
+    let result = if locked {
+        // Not every platform can lock the pointer, but confining it still
+        // keeps it from leaving the window
+        window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+    };
+
+    match result {
+        Ok(()) => {
+            window.set_cursor_visible(!locked);
+            controls.set_pointer_locked(locked);
+        }
+        Err(error) => {
+            tracing::warn!(?error, "Failed to lock the pointer");
+            controls.set_pointer_locked(false);
+        }
+    }
+}
+
+/// Writes the bookmarks next to the model, where loading it reads them from.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_bookmarks(renderer: &Renderer, model_path: Option<&std::path::Path>) {
+    if let Some(path) = model_path {
+        if let Err(error) = crate::bookmark::save(path, renderer.bookmarks()) {
+            tracing::warn!(?error, "Failed to write bookmarks");
+        }
+    }
+}
+
+fn set_variant(renderer: &mut Renderer, name: Option<&str>) {
+    if !renderer.set_variant(name) {
+        tracing::warn!(?name, "Model has no such material variant");
+    }
+}
+
+fn set_camera(renderer: &mut Renderer, name: Option<&str>) {
+    if !renderer.set_camera(name) {
+        tracing::warn!(?name, "Model has no such camera");
+    }
+}
@@ -0,0 +1,182 @@
+use std::path;
+
+use anyhow::anyhow;
+use serde_json::{json, Value};
+
+use crate::camera::ProjectionMode;
+use crate::Result;
+
+/// A named view of the scene, to come back to later.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub eye: glam::Vec3,
+    /// The point looked at, which the orbit camera turns around
+    pub target: glam::Vec3,
+    pub up: glam::Vec3,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    pub projection: ProjectionMode,
+}
+
+impl Bookmark {
+    pub fn to_json(&self) -> Value {
+        let projection = match self.projection {
+            ProjectionMode::Perspective => "perspective",
+            ProjectionMode::Orthographic => "orthographic",
+        };
+
+        json!({
+            "name": self.name,
+            "eye": self.eye.to_array(),
+            "target": self.target.to_array(),
+            "up": self.up.to_array(),
+            "fov": self.fov,
+            "projection": projection,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let name = value
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Missing or invalid `name`"))?;
+        let fov = value
+            .get("fov")
+            .and_then(Value::as_f64)
+            .ok_or_else(|| anyhow!("Missing or invalid `fov` of bookmark {name:?}"))?;
+        let projection = match value.get("projection").and_then(Value::as_str) {
+            Some("perspective") => ProjectionMode::Perspective,
+            Some("orthographic") => ProjectionMode::Orthographic,
+            projection => {
+                return Err(anyhow!(
+                    "Unknown projection {projection:?} of bookmark {name:?}"
+                ))
+            }
+        };
+
+        let vec3 = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_array)
+                .filter(|array| array.len() == 3)
+                .and_then(|array| {
+                    let components: Option<Vec<f32>> = array
+                        .iter()
+                        .map(|component| component.as_f64().map(|component| component as f32))
+                        .collect();
+                    components.map(|components| glam::Vec3::from_slice(&components))
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid `{key}` of bookmark {name:?}"))
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            eye: vec3("eye")?,
+            target: vec3("target")?,
+            up: vec3("up")?,
+            fov: fov as f32,
+            projection,
+        })
+    }
+}
+
+/// Name of the bookmark that number key `key` recalls and Ctrl+`key`
+/// saves, so that a slot keeps its bookmark wherever it is in the list.
+pub fn slot_name(key: usize) -> String {
+    key.to_string()
+}
+
+/// Adds `bookmark`, replacing any bookmark of the same name.
+pub fn insert(bookmarks: &mut Vec<Bookmark>, bookmark: Bookmark) {
+    match bookmarks
+        .iter_mut()
+        .find(|saved| saved.name == bookmark.name)
+    {
+        Some(saved) => *saved = bookmark,
+        None => bookmarks.push(bookmark),
+    }
+}
+
+pub fn find<'a>(bookmarks: &'a [Bookmark], name: &str) -> Option<&'a Bookmark> {
+    bookmarks.iter().find(|bookmark| bookmark.name == name)
+}
+
+/// Writes `bookmarks` as a JSON array.
+pub fn to_json(bookmarks: &[Bookmark]) -> String {
+    let bookmarks: Vec<Value> = bookmarks.iter().map(Bookmark::to_json).collect();
+    serde_json::to_string_pretty(&bookmarks).expect("JSON values always serialize")
+}
+
+/// Reads bookmarks from a JSON array, as written by [`to_json`].
+pub fn from_json(json: &str) -> Result<Vec<Bookmark>> {
+    let value: Value = serde_json::from_str(json)?;
+    value
+        .as_array()
+        .ok_or_else(|| anyhow!("Expected an array of bookmarks"))?
+        .iter()
+        .map(Bookmark::from_json)
+        .collect()
+}
+
+/// Where the bookmarks of the model at `model_path` are kept: next to it,
+/// as `model.bookmarks.json` for `model.gltf`.
+pub fn path_for(model_path: &path::Path) -> path::PathBuf {
+    model_path.with_extension("bookmarks.json")
+}
+
+/// Reads the bookmarks of the model at `model_path`, or none if it has no
+/// bookmarks file yet.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(model_path: &path::Path) -> Result<Vec<Bookmark>> {
+    match std::fs::read_to_string(path_for(model_path)) {
+        Ok(json) => from_json(&json),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Writes the bookmarks file of the model at `model_path`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(model_path: &path::Path, bookmarks: &[Bookmark]) -> Result<()> {
+    std::fs::write(path_for(model_path), to_json(bookmarks))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(name: String, eye: glam::Vec3) -> Bookmark {
+        Bookmark {
+            name,
+            eye,
+            target: glam::Vec3::ZERO,
+            up: glam::Vec3::Y,
+            fov: 45.0,
+            projection: ProjectionMode::Perspective,
+        }
+    }
+
+    #[test]
+    fn number_keys_recall_the_bookmark_they_saved() {
+        // Loaded from the bookmarks file, ahead of any numbered slot
+        let mut bookmarks = vec![bookmark("front".to_string(), glam::Vec3::Z)];
+
+        // Ctrl+3, then Ctrl+1
+        insert(&mut bookmarks, bookmark(slot_name(3), glam::Vec3::X));
+        insert(&mut bookmarks, bookmark(slot_name(1), glam::Vec3::Y));
+
+        assert_eq!(find(&bookmarks, &slot_name(1)).unwrap().eye, glam::Vec3::Y);
+        assert_eq!(find(&bookmarks, &slot_name(3)).unwrap().eye, glam::Vec3::X);
+        assert!(find(&bookmarks, &slot_name(2)).is_none());
+
+        // Ctrl+3 again overwrites its slot
+        insert(&mut bookmarks, bookmark(slot_name(3), glam::Vec3::NEG_X));
+        assert_eq!(bookmarks.len(), 3);
+        assert_eq!(
+            find(&bookmarks, &slot_name(3)).unwrap().eye,
+            glam::Vec3::NEG_X
+        );
+    }
+}
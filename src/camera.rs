@@ -1,108 +1,1083 @@
-use crate::uniform::{CameraBinding, Uniforms};
+use crate::bookmark::Bookmark;
+use crate::bounds::{Aabb, BoundingSphere};
+use crate::controls::CameraMotion;
+use crate::depth::DepthMode;
+use crate::resources::Projection;
+use crate::uniform::CameraBinding;
 
+/// Something to view the scene through.
+pub trait Camera {
+    fn view_matrix(&self) -> glam::Mat4;
+
+    /// The projection for a viewport of `aspect_ratio`, width over height.
+    fn projection_matrix(&self, aspect_ratio: f32, depth_mode: DepthMode) -> glam::Mat4;
+
+    fn uniforms_data(&self, aspect_ratio: f32, depth_mode: DepthMode) -> CameraBinding {
+        CameraBinding {
+            view_matrix: self.view_matrix(),
+            projection_matrix: self.projection_matrix(aspect_ratio, depth_mode),
+            viewport: CameraBinding::FULL_VIEWPORT,
+        }
+    }
+
+    /// The uniforms of an eye `offset` to the right of the camera, for
+    /// stereo views. Both eyes look ahead in parallel, with their
+    /// projections shifted to meet `convergence` ahead, where the scene
+    /// appears at the depth of the screen.
+    fn eye_uniforms_data(
+        &self,
+        aspect_ratio: f32,
+        depth_mode: DepthMode,
+        offset: f32,
+        convergence: f32,
+    ) -> CameraBinding {
+        let data = self.uniforms_data(aspect_ratio, depth_mode);
+        // The view looks down +Z with +X to the right
+        let view_matrix =
+            glam::Mat4::from_translation(glam::Vec3::new(-offset, 0.0, 0.0)) * data.view_matrix;
+        let focus =
+            data.projection_matrix
+                .project_point3(glam::Vec3::new(-offset, 0.0, convergence));
+        // Translating clip space moves points by their w, which puts the
+        // focus in the center at any depth mode
+        let projection_matrix = glam::Mat4::from_translation(glam::Vec3::new(-focus.x, 0.0, 0.0))
+            * data.projection_matrix;
+
+        CameraBinding {
+            view_matrix,
+            projection_matrix,
+            ..data
+        }
+    }
+}
+
+/// Near and far plane distances for a perspective projection from `eye`, as
+/// tight around `sphere` as the eye position allows.
+fn fit_clip_planes(eye: glam::Vec3, sphere: &BoundingSphere) -> (f32, f32) {
+    let distance = eye.distance(sphere.center);
+    // Padded, so that the outermost surfaces aren't clipped
+    let z_far = (distance + sphere.radius) * 1.01;
+    // Keeps the near plane from approaching zero once the eye is inside the
+    // sphere
+    let z_near = ((distance - sphere.radius) * 0.99).max(z_far * MIN_Z_NEAR_RATIO);
+    (z_near, z_far)
+}
+
+const FOV: f32 = 45.0;
+const Z_NEAR: f32 = 0.1;
+const Z_FAR: f32 = 100.0;
+/// Smallest near plane distance relative to the far plane
+const MIN_Z_NEAR_RATIO: f32 = 0.001;
+
+/// A camera of the glTF file, placed in the world by the node it is
+/// attached to.
+#[derive(Clone, Debug)]
+pub struct NodeCamera {
+    pub name: Option<String>,
+    pub projection: Projection,
+    /// World transform of the node. The camera looks down its -Z axis, with
+    /// +Y up.
+    pub transform: glam::Mat4,
+}
+
+impl Camera for NodeCamera {
+    fn view_matrix(&self) -> glam::Mat4 {
+        let eye = self.transform.transform_point3(glam::Vec3::ZERO);
+        let forward = self.transform.transform_vector3(glam::Vec3::NEG_Z);
+        let up = self.transform.transform_vector3(glam::Vec3::Y);
+
+        glam::Mat4::look_to_lh(eye, forward, up)
+    }
+
+    fn projection_matrix(&self, aspect_ratio: f32, depth_mode: DepthMode) -> glam::Mat4 {
+        self.projection.matrix(aspect_ratio, depth_mode)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProjectionMode {
+    #[default]
+    Perspective,
+    /// Shows as much around the target as the perspective projection would
+    Orthographic,
+}
+
+/// Preset viewing directions, like the numpad views of DCC tools. The model
+/// faces the front view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum View {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl View {
+    /// Yaw and pitch of an [`ArcCamera`] looking from this side
+    pub fn angles(&self) -> (f32, f32) {
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        // glTF models face +Z with their right side at -X, which the model
+        // root mirrors to +X
+        match self {
+            View::Front => (0.0, 0.0),
+            View::Back => (PI, 0.0),
+            View::Left => (-FRAC_PI_2, 0.0),
+            View::Right => (FRAC_PI_2, 0.0),
+            View::Top => (0.0, FRAC_PI_2),
+            View::Bottom => (0.0, -FRAC_PI_2),
+        }
+    }
+}
+
+/// How dragging turns the [`ArcCamera`] around its target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrbitMode {
+    /// Dragging sideways turns around the world up axis and up and down
+    /// tilts towards the poles, keeping the horizon level
+    #[default]
+    Turntable,
+    /// Dragging turns around the camera's own axes, free to roll and to
+    /// go over the poles
+    Trackball,
+}
+
+impl std::str::FromStr for OrbitMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "turntable" => Ok(OrbitMode::Turntable),
+            "trackball" => Ok(OrbitMode::Trackball),
+            _ => Err(anyhow::anyhow!(
+                "Unknown orbit mode {s:?}, expected turntable or trackball"
+            )),
+        }
+    }
+}
+
+/// Spherical coordinates of the eye around the target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Angles {
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+}
+
+/// Where an [`ArcCamera`] is placed and how wide it sees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pose {
+    target: glam::Vec3,
+    angles: Angles,
+    distance: f32,
+    fov: f32,
+}
+
+/// An animated change of view.
+#[derive(Clone, Copy, Debug)]
+struct ViewTransition {
+    from: Pose,
+    to: Pose,
+    /// From 0 to 1
+    progress: f32,
+}
+
+/// A camera orbiting a target, placed by spherical coordinates around it.
+#[derive(Clone, Debug)]
 pub struct ArcCamera {
-    pub eye: glam::Vec3,
     pub target: glam::Vec3,
-    pub up: glam::Vec3,
+    /// Radians the eye is turned around the world up axis, zero looking
+    /// from +Z and positive towards +X
+    pub yaw: f32,
+    /// Radians the eye is raised above the target's horizon
+    pub pitch: f32,
+    /// Radians the camera is rolled around its viewing direction, which
+    /// only trackball orbiting does
+    pub roll: f32,
+    /// Distance from the eye to the target
+    pub distance: f32,
+    /// Vertical field of view in degrees
+    pub fov: f32,
     pub width: u32,
     pub height: u32,
-    pub uniforms: Uniforms<CameraBinding>,
+    /// What the near and far planes are fitted around, if anything
+    pub bounding_sphere: Option<BoundingSphere>,
+    pub projection: ProjectionMode,
+    pub orbit_mode: OrbitMode,
+    transition: Option<ViewTransition>,
 }
 
 impl ArcCamera {
-    const FOV: f32 = 45.0;
-    const Z_NEAR: f32 = 0.1;
-    const Z_FAR: f32 = 100.0;
-
-    pub fn new(
-        width: u32,
-        height: u32,
-        device: &wgpu::Device,
-        layout: &wgpu::BindGroupLayout,
-    ) -> Self {
-        let eye = glam::Vec3::new(0.0, 0.0, 0.25);
-        let target = glam::Vec3::new(0.0, 0.05, 0.0);
-        let up = glam::Vec3::new(0.0, 1.0, 0.0);
-
-        let view_matrix = Self::calculate_view_matrix(eye, target, up);
-        let projection_matrix = Self::calculate_projection_matrix(width, height);
-
-        let uniforms = Uniforms::new(
-            CameraBinding {
-                view_matrix,
-                projection_matrix,
-            },
-            device,
-            layout,
-        );
+    /// Seconds to animate a change of view over
+    const TRANSITION_DURATION: f32 = 0.3;
+    /// Keeps the eye from passing distance zero, where the viewing
+    /// direction is lost
+    const MIN_DISTANCE: f32 = 1e-6;
 
-        Self {
-            eye,
-            target,
-            up,
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut camera = Self {
+            target: glam::Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            distance: 1.0,
+            fov: FOV,
             width,
             height,
-            uniforms,
+            bounding_sphere: None,
+            projection: ProjectionMode::Perspective,
+            orbit_mode: OrbitMode::Turntable,
+            transition: None,
+        };
+        camera.look_at(
+            glam::Vec3::new(0.0, 0.0, 0.25),
+            glam::Vec3::new(0.0, 0.05, 0.0),
+        );
+        camera
+    }
+
+    /// Places the eye at `eye`, looking at `target` with the horizon level.
+    pub fn look_at(&mut self, eye: glam::Vec3, target: glam::Vec3) {
+        let offset = eye - target;
+        let direction = offset.try_normalize().unwrap_or(glam::Vec3::Z);
+
+        self.target = target;
+        self.distance = offset.length().max(Self::MIN_DISTANCE);
+        self.yaw = direction.x.atan2(direction.z);
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+        self.roll = 0.0;
+        self.transition = None;
+    }
+
+    /// The rotation taking +Z to the direction from the target to the eye,
+    /// and +Y to the camera's up direction.
+    pub fn orientation(&self) -> glam::Quat {
+        Self::angles_orientation(self.angles())
+    }
+
+    fn angles(&self) -> Angles {
+        Angles {
+            yaw: self.yaw,
+            pitch: self.pitch,
+            roll: self.roll,
         }
     }
 
-    pub fn view_matrix(&self) -> glam::Mat4 {
-        Self::calculate_view_matrix(self.eye, self.target, self.up)
+    fn set_angles(&mut self, angles: Angles) {
+        self.yaw = angles.yaw;
+        self.pitch = angles.pitch;
+        self.roll = angles.roll;
     }
 
-    fn calculate_view_matrix(eye: glam::Vec3, target: glam::Vec3, up: glam::Vec3) -> glam::Mat4 {
-        glam::Mat4::look_at_lh(eye, target, up)
+    fn pose(&self) -> Pose {
+        Pose {
+            target: self.target,
+            angles: self.angles(),
+            distance: self.distance,
+            fov: self.fov,
+        }
     }
 
-    pub fn projection_matrix(&self) -> glam::Mat4 {
-        Self::calculate_projection_matrix(self.width, self.height)
+    fn set_pose(&mut self, pose: Pose) {
+        self.target = pose.target;
+        self.set_angles(pose.angles);
+        self.distance = pose.distance;
+        self.fov = pose.fov;
     }
 
-    fn calculate_projection_matrix(width: u32, height: u32) -> glam::Mat4 {
-        glam::Mat4::perspective_lh(
-            Self::FOV.to_radians(),
-            width as f32 / height as f32,
-            Self::Z_NEAR,
-            Self::Z_FAR,
-        )
+    fn angles_orientation(angles: Angles) -> glam::Quat {
+        // Raising the eye turns +Z towards +Y, which is a negative turn
+        // around +X
+        glam::Quat::from_euler(glam::EulerRot::YXZ, angles.yaw, -angles.pitch, angles.roll)
     }
 
-    fn uniforms_data(&self) -> CameraBinding {
-        CameraBinding {
-            view_matrix: self.view_matrix(),
-            projection_matrix: self.projection_matrix(),
+    pub fn eye(&self) -> glam::Vec3 {
+        self.target + self.orientation() * glam::Vec3::Z * self.distance
+    }
+
+    pub fn up(&self) -> glam::Vec3 {
+        self.orientation() * glam::Vec3::Y
+    }
+
+    /// The camera's right direction on screen.
+    pub fn right(&self) -> glam::Vec3 {
+        // The view is left-handed, looking down -Z of the orientation
+        self.orientation() * glam::Vec3::NEG_X
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    /// Half the width and height of the orthographic view volume: what the
+    /// perspective projection shows at the target, so zooming scales it.
+    pub fn orthographic_extent(&self, aspect_ratio: f32) -> (f32, f32) {
+        let half_height = self.distance * (self.fov.to_radians() * 0.5).tan();
+        (half_height * aspect_ratio, half_height)
+    }
+
+    /// The near and far plane distances, as tight around the bounding
+    /// sphere as the eye position allows.
+    pub fn clip_planes(&self) -> (f32, f32) {
+        match (&self.bounding_sphere, self.projection) {
+            (Some(sphere), ProjectionMode::Perspective) => fit_clip_planes(self.eye(), sphere),
+            (Some(sphere), ProjectionMode::Orthographic) => {
+                let distance = self.eye().distance(sphere.center);
+                // Nothing diverges at the eye, so the near plane may lie
+                // behind it. Padded, so that the outermost surfaces aren't
+                // clipped.
+                let radius = sphere.radius * 1.01;
+                (distance - radius, distance + radius)
+            }
+            (None, _) => (Z_NEAR, Z_FAR),
+        }
+    }
+
+    pub fn set_projection(&mut self, projection: ProjectionMode) {
+        self.projection = projection;
+    }
+
+    pub fn toggle_projection(&mut self) {
+        let projection = match self.projection {
+            ProjectionMode::Perspective => ProjectionMode::Orthographic,
+            ProjectionMode::Orthographic => ProjectionMode::Perspective,
+        };
+        self.set_projection(projection);
+    }
+
+    /// Switches how dragging orbits. Going back to turntable orbiting
+    /// levels the horizon.
+    pub fn set_orbit_mode(&mut self, orbit_mode: OrbitMode) {
+        self.orbit_mode = orbit_mode;
+        if orbit_mode == OrbitMode::Turntable {
+            self.set_angles(Self::level(self.orientation()));
+        }
+    }
+
+    /// Yaw and pitch of the viewing direction of `orientation`, without roll.
+    fn level(orientation: glam::Quat) -> Angles {
+        Angles {
+            roll: 0.0,
+            ..Self::orientation_angles(orientation)
+        }
+    }
+
+    /// The angles of `orientation`. Unlike a plain Euler decomposition, this
+    /// stays precise looking straight down or up.
+    fn orientation_angles(orientation: glam::Quat) -> Angles {
+        let direction = orientation * glam::Vec3::Z;
+        let up = orientation * glam::Vec3::Y;
+        let horizontal = direction.x.hypot(direction.z);
+
+        let pitch = direction.y.atan2(horizontal);
+        let yaw = if horizontal > 1e-5 {
+            direction.x.atan2(direction.z)
+        } else {
+            // Looking straight down or up, keep the screen's up direction,
+            // which points away from the eye or towards it
+            let up = up * -direction.y.signum();
+            up.x.atan2(up.z)
+        };
+
+        let level = Self::angles_orientation(Angles {
+            yaw,
+            pitch,
+            roll: 0.0,
+        });
+        let roll = (-up.dot(level * glam::Vec3::X)).atan2(up.dot(level * glam::Vec3::Y));
+
+        Angles { yaw, pitch, roll }
+    }
+
+    /// Starts turning the camera around the target to look from `view`.
+    pub fn set_view(&mut self, view: View) {
+        let (yaw, pitch) = view.angles();
+
+        self.transition_to(Pose {
+            angles: Angles {
+                yaw,
+                pitch,
+                roll: 0.0,
+            },
+            ..self.pose()
+        });
+    }
+
+    /// The current view, as a bookmark called `name`.
+    pub fn bookmark(&self, name: String) -> Bookmark {
+        Bookmark {
+            name,
+            eye: self.eye(),
+            target: self.target,
+            up: self.up(),
+            fov: self.fov,
+            projection: self.projection,
+        }
+    }
+
+    /// Starts moving the camera to the view of `bookmark`, switching to its
+    /// projection at once.
+    pub fn go_to(&mut self, bookmark: &Bookmark) {
+        self.projection = bookmark.projection;
+        self.transition_to(Self::bookmark_pose(bookmark));
+    }
+
+    /// Moves the camera to the view of `bookmark` at once, roll included.
+    pub fn restore(&mut self, bookmark: &Bookmark) {
+        self.projection = bookmark.projection;
+        self.set_pose(Self::bookmark_pose(bookmark));
+        self.transition = None;
+    }
+
+    fn bookmark_pose(bookmark: &Bookmark) -> Pose {
+        let offset = bookmark.eye - bookmark.target;
+        let direction = offset.try_normalize().unwrap_or(glam::Vec3::Z);
+        // Square the up direction with the viewing direction, falling back
+        // to a level view if they are parallel
+        let up = (bookmark.up - direction * bookmark.up.dot(direction))
+            .try_normalize()
+            .unwrap_or_else(|| direction.any_orthonormal_vector());
+        let orientation =
+            glam::Quat::from_mat3(&glam::Mat3::from_cols(up.cross(direction), up, direction));
+
+        Pose {
+            target: bookmark.target,
+            angles: Self::orientation_angles(orientation),
+            distance: offset.length().max(Self::MIN_DISTANCE),
+            fov: bookmark.fov,
         }
     }
 
-    pub fn resize_viewport(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
+    fn transition_to(&mut self, to: Pose) {
+        let from = self.pose();
+
+        self.transition = Some(ViewTransition {
+            from,
+            to: Pose {
+                // Take the short way around
+                angles: Angles {
+                    yaw: from.angles.yaw + wrap_angle(to.angles.yaw - from.angles.yaw),
+                    roll: from.angles.roll + wrap_angle(to.angles.roll - from.angles.roll),
+                    ..to.angles
+                },
+                ..to
+            },
+            progress: 0.0,
+        });
+    }
+
+    /// Advances animations by `delta_time` seconds. Returns whether the
+    /// camera is still moving, and so needs more frames.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let transition = match &mut self.transition {
+            Some(transition) => transition,
+            None => return false,
+        };
+
+        transition.progress =
+            (transition.progress + delta_time / Self::TRANSITION_DURATION).min(1.0);
+
+        // Smoothstep, easing in and out
+        let t = transition.progress * transition.progress * (3.0 - 2.0 * transition.progress);
+        let (from, to) = (transition.from, transition.to);
+        let finished = transition.progress >= 1.0;
+
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        self.set_pose(Pose {
+            target: from.target.lerp(to.target, t),
+            angles: Angles {
+                yaw: wrap_angle(lerp(from.angles.yaw, to.angles.yaw)),
+                pitch: lerp(from.angles.pitch, to.angles.pitch),
+                roll: wrap_angle(lerp(from.angles.roll, to.angles.roll)),
+            },
+            // Geometrically, so that zooming keeps the same pace near and far
+            distance: from.distance * (to.distance / from.distance).powf(t),
+            fov: lerp(from.fov, to.fov),
+        });
+
+        if finished {
+            self.transition = None;
+        }
+
+        self.transition.is_some()
+    }
+
+    /// Moves the camera back along its viewing direction until `bounds`
+    /// fits in view, and fits the near and far planes around it. An empty
+    /// `bounds` leaves the camera where it is.
+    pub fn frame(&mut self, bounds: &Aabb) {
+        if bounds.is_empty() {
+            return;
+        }
+
+        let sphere = bounds.bounding_sphere();
+        // Avoid a degenerate sphere for a single point or a flat line
+        let radius = sphere.radius.max(f32::EPSILON);
+
+        let aspect_ratio = self.aspect_ratio();
+        let half_fov_y = self.fov.to_radians() * 0.5;
+        let half_fov_x = (half_fov_y.tan() * aspect_ratio).atan();
+
+        self.target = sphere.center;
+        self.distance = radius / half_fov_y.min(half_fov_x).sin();
+        self.bounding_sphere = Some(BoundingSphere {
+            center: sphere.center,
+            radius,
+        });
+    }
+
+    /// An orthographic copy of the camera looking at the target from
+    /// `view`, as the side views of a quad view.
+    pub fn side_view(&self, view: View) -> ArcCamera {
+        let (yaw, pitch) = view.angles();
+
+        ArcCamera {
+            yaw,
+            pitch,
+            roll: 0.0,
+            projection: ProjectionMode::Orthographic,
+            transition: None,
+            ..self.clone()
+        }
+    }
+
+    pub fn resize_viewport(&mut self, width: u32, height: u32) {
         self.width = width;
         self.height = height;
-        self.uniforms.update(self.uniforms_data(), queue);
     }
 
-    pub fn rotate(&mut self, delta: glam::Vec2, queue: &wgpu::Queue) {
-        // calculate perpendicular axis to eye and up
-        let axis = self.eye.cross(self.up).normalize();
+    pub fn rotate(&mut self, delta: glam::Vec2) {
+        self.orbit(delta * 0.01);
+        tracing::debug!(yaw = self.yaw, pitch = self.pitch, "orbit");
+    }
+
+    /// Turns the eye around the target by `angles` in radians: sideways by
+    /// `angles.x` and upwards by `angles.y`.
+    pub fn orbit(&mut self, angles: glam::Vec2) {
+        match self.orbit_mode {
+            OrbitMode::Turntable => {
+                let max_pitch = std::f32::consts::FRAC_PI_2;
+                self.yaw = wrap_angle(self.yaw + angles.x);
+                self.pitch = (self.pitch + angles.y).clamp(-max_pitch, max_pitch);
+            }
+            OrbitMode::Trackball => {
+                // Around the camera's own up and right axes
+                let rotation =
+                    glam::Quat::from_rotation_y(angles.x) * glam::Quat::from_rotation_x(-angles.y);
+                self.set_angles(Self::orientation_angles(self.orientation() * rotation));
+            }
+        }
+    }
 
-        // calculate rotation from delta's x and y
-        let rotation = glam::Quat::from_axis_angle(axis, delta.y * 0.01)
-            * glam::Quat::from_axis_angle(self.up, delta.x * 0.01);
-        // * glam::Quat::from_rotation_y(delta.x * 0.01);
+    pub fn zoom(&mut self, delta: f32) {
+        self.distance = (self.distance * (1.0 - delta)).max(Self::MIN_DISTANCE);
+    }
 
-        let eye = rotation * (self.eye - self.target);
-        self.eye = eye + self.target;
+    /// Moves the target and eye in the view plane, following a drag of
+    /// `delta` pixels: the point at the target's depth stays under the cursor.
+    pub fn pan(&mut self, delta: glam::Vec2) {
+        let (_, half_height) = self.orthographic_extent(self.aspect_ratio());
+        let units_per_pixel = 2.0 * half_height / self.height.max(1) as f32;
 
-        self.up = (rotation * self.up).normalize();
-        tracing::debug!("eye: {:?}", self.eye);
+        // Dragging right moves the scene right, so the camera left
+        self.target += (self.up() * delta.y - self.right() * delta.x) * units_per_pixel;
+    }
 
-        self.uniforms.update(self.uniforms_data(), queue);
+    /// Applies a movement of the mouse controls. Flying is left to the fly
+    /// camera.
+    pub fn apply_motion(&mut self, motion: &CameraMotion) {
+        if motion.orbit != glam::Vec2::ZERO || motion.pan != glam::Vec2::ZERO || motion.zoom != 0.0
+        {
+            // The user took over from the animated view change
+            self.transition = None;
+        }
+        if motion.orbit != glam::Vec2::ZERO {
+            self.orbit(motion.orbit);
+        }
+        if motion.pan != glam::Vec2::ZERO {
+            self.pan(motion.pan);
+        }
+        if motion.zoom != 0.0 {
+            self.distance = (self.distance * (-motion.zoom).exp()).max(Self::MIN_DISTANCE);
+        }
     }
+}
+
+/// Wraps `angle` into -π..=π radians.
+fn wrap_angle(angle: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+
+    angle - TAU * ((angle + PI) / TAU).floor()
+}
+
+impl Camera for ArcCamera {
+    fn view_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::look_at_lh(self.eye(), self.target, self.up())
+    }
+
+    fn projection_matrix(&self, aspect_ratio: f32, depth_mode: DepthMode) -> glam::Mat4 {
+        let (z_near, z_far) = self.clip_planes();
+
+        match self.projection {
+            ProjectionMode::Perspective => {
+                depth_mode.perspective(self.fov.to_radians(), aspect_ratio, z_near, Some(z_far))
+            }
+            ProjectionMode::Orthographic => {
+                let (half_width, half_height) = self.orthographic_extent(aspect_ratio);
+                depth_mode.orthographic(half_width, half_height, z_near, z_far)
+            }
+        }
+    }
+}
+
+/// A first-person camera flying freely through the scene, for scenes too
+/// large to orbit around a single point.
+pub struct FlyCamera {
+    pub position: glam::Vec3,
+    /// Radians turned right from looking down +Z
+    pub yaw: f32,
+    /// Radians looked up from the horizon
+    pub pitch: f32,
+    /// Units flown per second
+    pub speed: f32,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    /// What the near and far planes are fitted around, if anything
+    pub bounding_sphere: Option<BoundingSphere>,
+}
+
+impl FlyCamera {
+    /// Keeps the view from flipping over when looking straight up or down
+    const MAX_PITCH: f32 = 89.0;
+    /// Seconds of flight per pixel dragged
+    const PAN_SPEED: f32 = 0.002;
+
+    /// A camera at `eye` looking at `target`.
+    pub fn looking_at(eye: glam::Vec3, target: glam::Vec3, speed: f32) -> Self {
+        let direction = (target - eye).try_normalize().unwrap_or(glam::Vec3::Z);
+
+        Self {
+            position: eye,
+            yaw: direction.x.atan2(direction.z),
+            pitch: direction.y.clamp(-1.0, 1.0).asin(),
+            speed,
+            fov: FOV,
+            bounding_sphere: None,
+        }
+    }
+
+    pub fn forward(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glam::Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch)
+    }
+
+    /// Right along the horizon
+    pub fn right(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        glam::Vec3::new(cos_yaw, 0.0, -sin_yaw)
+    }
+
+    /// Turns the camera right by `angles.x` and down by `angles.y` radians.
+    pub fn look(&mut self, angles: glam::Vec2) {
+        let max_pitch = Self::MAX_PITCH.to_radians();
+        self.yaw = (self.yaw + angles.x) % std::f32::consts::TAU;
+        self.pitch = (self.pitch - angles.y).clamp(-max_pitch, max_pitch);
+    }
+
+    /// Flies `seconds.x` right, `seconds.y` up and `seconds.z` forward at
+    /// the camera's speed. Up is the world's, forward where the camera looks.
+    pub fn fly(&mut self, seconds: glam::Vec3) {
+        let offset =
+            self.right() * seconds.x + glam::Vec3::Y * seconds.y + self.forward() * seconds.z;
+        self.position += offset * self.speed;
+    }
+
+    /// Applies a movement of the mouse and keyboard controls: orbiting looks
+    /// around, panning strafes and zooming changes the speed.
+    pub fn apply_motion(&mut self, motion: &CameraMotion) {
+        self.look(motion.orbit);
+
+        // Dragging right moves the scene right, so the camera left
+        let pan = glam::Vec3::new(-motion.pan.x, motion.pan.y, 0.0) * Self::PAN_SPEED;
+        self.fly(motion.fly + pan);
+
+        self.speed *= motion.zoom.exp();
+    }
+}
+
+impl Camera for FlyCamera {
+    fn view_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::look_to_lh(self.position, self.forward(), glam::Vec3::Y)
+    }
+
+    fn projection_matrix(&self, aspect_ratio: f32, depth_mode: DepthMode) -> glam::Mat4 {
+        let (z_near, z_far) = match &self.bounding_sphere {
+            Some(sphere) => fit_clip_planes(self.position, sphere),
+            None => (Z_NEAR, Z_FAR),
+        };
+
+        depth_mode.perspective(self.fov.to_radians(), aspect_ratio, z_near, Some(z_far))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const EPSILON: f32 = 1e-4;
+
+    fn camera() -> ArcCamera {
+        let mut camera = ArcCamera::new(800, 600);
+        camera.look_at(glam::Vec3::new(0.0, 0.0, 2.0), glam::Vec3::ZERO);
+        camera
+    }
+
+    fn assert_vec3_eq(actual: glam::Vec3, expected: glam::Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn look_at_round_trips() {
+        let mut camera = camera();
+        let eye = glam::Vec3::new(1.0, 2.0, -3.0);
+        let target = glam::Vec3::new(0.5, -1.0, 0.0);
+        camera.look_at(eye, target);
+
+        assert_vec3_eq(camera.eye(), eye);
+        assert_eq!(camera.target, target);
+        assert!((camera.distance - eye.distance(target)).abs() < EPSILON);
+        assert_eq!(camera.roll, 0.0);
+    }
+
+    #[test]
+    fn view_matrix_looks_at_target() {
+        let camera = camera();
+        let view = camera.view_matrix();
+
+        // Left-handed view space looks down +Z
+        assert_vec3_eq(
+            view.transform_point3(camera.target),
+            glam::Vec3::new(0.0, 0.0, 2.0),
+        );
+        // Up and right on screen
+        assert_vec3_eq(view.transform_vector3(camera.up()), glam::Vec3::Y);
+        assert_vec3_eq(view.transform_vector3(camera.right()), glam::Vec3::X);
+    }
+
+    #[test]
+    fn spherical_coordinates() {
+        let mut camera = camera();
+
+        camera.yaw = FRAC_PI_2;
+        assert_vec3_eq(camera.eye(), glam::Vec3::new(2.0, 0.0, 0.0));
+
+        camera.yaw = 0.0;
+        camera.pitch = FRAC_PI_2;
+        assert_vec3_eq(camera.eye(), glam::Vec3::new(0.0, 2.0, 0.0));
+        assert_vec3_eq(camera.up(), glam::Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn turntable_keeps_horizon_level() {
+        let mut camera = camera();
+
+        for _ in 0..100 {
+            camera.orbit(glam::Vec2::new(0.3, 0.2));
+            assert!(camera.right().y.abs() < EPSILON, "{}", camera.right());
+            assert!(camera.up().y >= -EPSILON, "{}", camera.up());
+        }
+
+        // Clamped at the pole rather than flipping over it
+        assert!((camera.pitch - FRAC_PI_2).abs() < EPSILON);
+        assert!(camera.yaw.abs() <= PI);
+    }
+
+    #[test]
+    fn turntable_full_turn_returns() {
+        let mut camera = camera();
+        camera.orbit(glam::Vec2::new(0.0, 0.5));
+        let eye = camera.eye();
+
+        for _ in 0..8 {
+            camera.orbit(glam::Vec2::new(PI / 4.0, 0.0));
+        }
+
+        assert_vec3_eq(camera.eye(), eye);
+    }
+
+    #[test]
+    fn trackball_goes_over_the_poles() {
+        let mut camera = camera();
+        camera.set_orbit_mode(OrbitMode::Trackball);
+
+        // Up and over the top, to look from behind upside down
+        for _ in 0..4 {
+            camera.orbit(glam::Vec2::new(0.0, PI / 4.0));
+        }
+
+        assert_vec3_eq(camera.eye(), glam::Vec3::new(0.0, 0.0, -2.0));
+        assert_vec3_eq(camera.up(), glam::Vec3::NEG_Y);
+
+        // And back
+        for _ in 0..4 {
+            camera.orbit(glam::Vec2::new(0.0, -PI / 4.0));
+        }
+
+        assert_vec3_eq(camera.eye(), glam::Vec3::new(0.0, 0.0, 2.0));
+        assert_vec3_eq(camera.up(), glam::Vec3::Y);
+    }
+
+    #[test]
+    fn trackball_matches_turntable_at_the_horizon() {
+        let mut turntable = camera();
+        let mut trackball = camera();
+        trackball.set_orbit_mode(OrbitMode::Trackball);
+
+        turntable.orbit(glam::Vec2::new(0.4, 0.0));
+        trackball.orbit(glam::Vec2::new(0.4, 0.0));
+        assert_vec3_eq(trackball.eye(), turntable.eye());
+
+        turntable.orbit(glam::Vec2::new(0.0, 0.3));
+        trackball.orbit(glam::Vec2::new(0.0, 0.3));
+        assert_vec3_eq(trackball.eye(), turntable.eye());
+    }
+
+    #[test]
+    fn turntable_levels_trackball_roll() {
+        let mut camera = camera();
+        camera.set_orbit_mode(OrbitMode::Trackball);
+        camera.orbit(glam::Vec2::new(0.0, 0.5));
+        camera.orbit(glam::Vec2::new(0.7, 0.0));
+        let direction = (camera.eye() - camera.target).normalize();
+        assert!(camera.right().y.abs() > 0.01, "rolled");
+
+        camera.set_orbit_mode(OrbitMode::Turntable);
+
+        // Same viewing direction, level horizon
+        assert_vec3_eq((camera.eye() - camera.target).normalize(), direction);
+        assert!(camera.right().y.abs() < EPSILON);
+        assert_eq!(camera.roll, 0.0);
+    }
+
+    #[test]
+    fn turntable_levels_looking_straight_down() {
+        let mut camera = camera();
+        camera.set_orbit_mode(OrbitMode::Trackball);
+        camera.orbit(glam::Vec2::new(0.0, FRAC_PI_2));
+        camera.orbit(glam::Vec2::new(0.0, PI / 4.0));
+        camera.orbit(glam::Vec2::new(0.0, -PI / 4.0));
+        let up = camera.up();
+
+        camera.set_orbit_mode(OrbitMode::Turntable);
+
+        assert_vec3_eq(camera.eye(), glam::Vec3::new(0.0, 2.0, 0.0));
+        assert_vec3_eq(camera.up(), up);
+    }
+
+    #[test]
+    fn views() {
+        let mut camera = camera();
+
+        for (view, direction, up) in [
+            (View::Front, glam::Vec3::Z, glam::Vec3::Y),
+            (View::Back, glam::Vec3::NEG_Z, glam::Vec3::Y),
+            (View::Left, glam::Vec3::NEG_X, glam::Vec3::Y),
+            (View::Right, glam::Vec3::X, glam::Vec3::Y),
+            (View::Top, glam::Vec3::Y, glam::Vec3::NEG_Z),
+            (View::Bottom, glam::Vec3::NEG_Y, glam::Vec3::Z),
+        ] {
+            camera.set_view(view);
+            assert!(camera.update(ArcCamera::TRANSITION_DURATION * 0.5));
+            assert!(!camera.update(ArcCamera::TRANSITION_DURATION));
+
+            assert_vec3_eq((camera.eye() - camera.target) / camera.distance, direction);
+            assert_vec3_eq(camera.up(), up);
+        }
+    }
+
+    #[test]
+    fn view_transition_takes_the_short_way() {
+        let mut camera = camera();
+        camera.yaw = PI * 0.9;
+
+        camera.set_view(View::Back);
+        camera.update(ArcCamera::TRANSITION_DURATION * 0.5);
+
+        assert!(camera.yaw.abs() > PI * 0.9, "{}", camera.yaw);
+    }
+
+    #[test]
+    fn bookmark_restores_the_view() {
+        let mut camera = camera();
+        camera.set_orbit_mode(OrbitMode::Trackball);
+        camera.orbit(glam::Vec2::new(0.7, 2.0));
+        camera.pan(glam::Vec2::new(30.0, -20.0));
+        camera.fov = 30.0;
+        camera.set_projection(ProjectionMode::Orthographic);
+        let bookmark = camera.bookmark("rolled".to_string());
+
+        let mut restored = ArcCamera::new(800, 600);
+        restored.restore(&bookmark);
+
+        assert_vec3_eq(restored.eye(), camera.eye());
+        assert_vec3_eq(restored.target, camera.target);
+        assert_vec3_eq(restored.up(), camera.up());
+        assert_eq!(restored.fov, 30.0);
+        assert_eq!(restored.projection, ProjectionMode::Orthographic);
+    }
+
+    #[test]
+    fn go_to_bookmark_animates() {
+        let mut camera = camera();
+        let bookmark = Bookmark {
+            name: "side".to_string(),
+            eye: glam::Vec3::new(5.0, 1.0, 0.0),
+            target: glam::Vec3::new(1.0, 1.0, 0.0),
+            // Not square with the viewing direction
+            up: glam::Vec3::new(1.0, 1.0, 0.0),
+            fov: 60.0,
+            projection: ProjectionMode::Perspective,
+        };
+
+        camera.go_to(&bookmark);
+        assert!(camera.update(ArcCamera::TRANSITION_DURATION * 0.5));
+        assert!(camera.distance > 2.0 && camera.distance < 4.0);
+        assert!(camera.fov > FOV && camera.fov < 60.0);
+        assert!(!camera.update(ArcCamera::TRANSITION_DURATION));
+
+        assert_vec3_eq(camera.eye(), bookmark.eye);
+        assert_vec3_eq(camera.target, bookmark.target);
+        assert_vec3_eq(camera.up(), glam::Vec3::Y);
+        assert!((camera.fov - 60.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn zoom_and_pan() {
+        let mut camera = camera();
+
+        camera.zoom(0.5);
+        assert!((camera.distance - 1.0).abs() < EPSILON);
+
+        // Dragging right by half the viewport height moves the view by the
+        // visible half height at the target
+        let (_, half_height) = camera.orthographic_extent(camera.aspect_ratio());
+        camera.pan(glam::Vec2::new(300.0, 0.0));
+        assert_vec3_eq(camera.target, -camera.right() * half_height);
+        assert!((camera.distance - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn projection_maps_target_to_center() {
+        let mut camera = camera();
+        camera.orbit(glam::Vec2::new(0.3, -0.4));
+
+        for projection in [ProjectionMode::Perspective, ProjectionMode::Orthographic] {
+            for depth_mode in [DepthMode::Standard, DepthMode::Reversed] {
+                camera.set_projection(projection);
+                let ndc = project(&camera, camera.target, depth_mode);
+
+                assert!(ndc.x.abs() < EPSILON && ndc.y.abs() < EPSILON, "{ndc}");
+                assert!((0.0..=1.0).contains(&ndc.z), "{ndc}");
+            }
+        }
+    }
+
+    #[test]
+    fn stereo_eyes_converge_on_the_target() {
+        let mut camera = camera();
+        camera.orbit(glam::Vec2::new(0.3, -0.4));
+        let behind = camera.target - (camera.eye() - camera.target);
+
+        for depth_mode in [DepthMode::Standard, DepthMode::Reversed] {
+            let [left, right] = [-0.05, 0.05].map(|offset| {
+                let data = camera.eye_uniforms_data(1.0, depth_mode, offset, camera.distance);
+                let clip_matrix = data.projection_matrix * data.view_matrix;
+                (
+                    clip_matrix.project_point3(camera.target),
+                    clip_matrix.project_point3(behind),
+                )
+            });
+
+            assert!(left.0.x.abs() < EPSILON && right.0.x.abs() < EPSILON);
+            // Behind the target, each eye sees more towards its own side
+            assert!(left.1.x < 0.0 && right.1.x > 0.0, "{left:?} {right:?}");
+        }
+    }
+
+    #[test]
+    fn side_view_looks_at_the_target() {
+        let mut camera = camera();
+        camera.orbit(glam::Vec2::new(0.3, -0.4));
+
+        let top = camera.side_view(View::Top);
+        assert_eq!(top.projection, ProjectionMode::Orthographic);
+        assert_vec3_eq(top.target, camera.target);
+        assert_vec3_eq(top.eye(), camera.target + glam::Vec3::Y * camera.distance);
+        assert_eq!(camera.projection, ProjectionMode::Perspective);
+    }
+
+    #[test]
+    fn reversed_depth_decreases_with_distance() {
+        let mut camera = camera();
+        let direction = (camera.eye() - camera.target).normalize();
+
+        for projection in [ProjectionMode::Perspective, ProjectionMode::Orthographic] {
+            camera.set_projection(projection);
+
+            let near = project(&camera, camera.target + direction, DepthMode::Reversed);
+            let far = project(&camera, camera.target - direction, DepthMode::Reversed);
+            assert!(near.z > far.z, "{near} {far}");
+            assert_eq!(
+                DepthMode::Reversed.compare(),
+                wgpu::CompareFunction::Greater
+            );
+
+            let near = project(&camera, camera.target + direction, DepthMode::Standard);
+            let far = project(&camera, camera.target - direction, DepthMode::Standard);
+            assert!(near.z < far.z, "{near} {far}");
+        }
+
+        // Nothing is too far for the reversed perspective projection
+        camera.set_projection(ProjectionMode::Perspective);
+        let distant = project(&camera, direction * -1e6, DepthMode::Reversed);
+        assert!(distant.z > 0.0 && distant.z < 1e-6, "{distant}");
+    }
+
+    fn project(camera: &ArcCamera, point: glam::Vec3, depth_mode: DepthMode) -> glam::Vec3 {
+        let data = camera.uniforms_data(camera.aspect_ratio(), depth_mode);
+        let clip = data.projection_matrix * data.view_matrix * point.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    #[test]
+    fn frame_fits_bounds() {
+        let mut camera = camera();
+        camera.orbit(glam::Vec2::new(1.0, 0.5));
+        let direction = (camera.eye() - camera.target).normalize();
+        let bounds = Aabb {
+            min: glam::Vec3::new(1.0, 1.0, 1.0),
+            max: glam::Vec3::new(3.0, 2.0, 5.0),
+        };
+
+        camera.frame(&bounds);
 
-    pub fn zoom(&mut self, delta: f32, queue: &wgpu::Queue) {
-        let eye = self.eye - self.target;
-        let eye = eye.normalize() * eye.length() * (1.0 - delta);
-        self.eye = eye + self.target;
+        let sphere = bounds.bounding_sphere();
+        assert_vec3_eq(camera.target, sphere.center);
+        assert_vec3_eq((camera.eye() - camera.target).normalize(), direction);
+        // The sphere touches the top and bottom of the view
+        let half_fov = FOV.to_radians() * 0.5;
+        assert!((sphere.radius / camera.distance - half_fov.sin()).abs() < EPSILON);
 
-        self.uniforms.update(self.uniforms_data(), queue);
+        let (z_near, z_far) = camera.clip_planes();
+        assert!(z_near < camera.distance - sphere.radius);
+        assert!(z_far > camera.distance + sphere.radius);
     }
 }
@@ -0,0 +1,296 @@
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode};
+
+/// A camera movement, in input units so that it can be split across frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CameraMotion {
+    /// Radians around the up axis and the camera's right axis
+    pub orbit: glam::Vec2,
+    /// Pixels to drag the view by
+    pub pan: glam::Vec2,
+    /// Natural logarithm of how many times closer to move to the target
+    pub zoom: f32,
+    /// Seconds to fly right, up and forward at the fly camera's speed
+    pub fly: glam::Vec3,
+}
+
+impl CameraMotion {
+    fn scale(&self, factor: f32) -> CameraMotion {
+        CameraMotion {
+            orbit: self.orbit * factor,
+            pan: self.pan * factor,
+            zoom: self.zoom * factor,
+            fly: self.fly * factor,
+        }
+    }
+
+    fn is_negligible(&self) -> bool {
+        self.orbit.length() < 1e-5 && self.pan.length() < 1e-2 && self.zoom.abs() < 1e-5
+    }
+}
+
+/// How strongly the camera follows the mouse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ControlSettings {
+    /// Multiplier on the orbit speed
+    pub orbit_sensitivity: f32,
+    /// Multiplier on the pan speed, where 1 keeps the point under the cursor
+    /// at the target's depth under it
+    pub pan_sensitivity: f32,
+    /// Multiplier on the zoom speed
+    pub zoom_sensitivity: f32,
+    /// Seconds it takes the camera to cover most of a movement. Zero moves
+    /// it at once.
+    pub damping: f32,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            orbit_sensitivity: 1.0,
+            pan_sensitivity: 1.0,
+            zoom_sensitivity: 1.0,
+            damping: 0.08,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragMode {
+    Orbit,
+    Pan,
+    Zoom,
+}
+
+/// Keys flying the fly camera, and the directions they fly it in
+const FLY_KEYS: [(VirtualKeyCode, glam::Vec3); 6] = [
+    (VirtualKeyCode::W, glam::Vec3::Z),
+    (VirtualKeyCode::S, glam::Vec3::NEG_Z),
+    (VirtualKeyCode::A, glam::Vec3::NEG_X),
+    (VirtualKeyCode::D, glam::Vec3::X),
+    (VirtualKeyCode::E, glam::Vec3::Y),
+    (VirtualKeyCode::Q, glam::Vec3::NEG_Y),
+];
+
+/// Turns mouse and keyboard input into damped camera motion: left drag
+/// orbits, right or middle drag pans, and the wheel zooms. Shift+left drag
+/// pans and Ctrl+left drag zooms, for touchpads and single-button mice.
+///
+/// WASD fly the fly camera horizontally and QE down and up, faster with
+/// Shift and slower with Ctrl.
+#[derive(Debug, Default)]
+pub struct Controls {
+    pub settings: ControlSettings,
+    modifiers: ModifiersState,
+    drag: Option<DragMode>,
+    cursor: Option<glam::Vec2>,
+    /// While the pointer is locked, dragging follows the raw mouse motion
+    /// rather than the cursor
+    pointer_locked: bool,
+    /// Fly keys held down
+    fly_keys: Vec<VirtualKeyCode>,
+    /// Motion still to be applied
+    pending: CameraMotion,
+}
+
+impl Controls {
+    /// Radians per pixel dragged
+    const ORBIT_SPEED: f32 = 0.01;
+    /// Radians per pixel of mouse motion while the pointer is locked
+    const LOOK_SPEED: f32 = 0.003;
+    /// Zoom per pixel dragged
+    const ZOOM_SPEED: f32 = 0.01;
+    /// Zoom per wheel line
+    const WHEEL_ZOOM_SPEED: f32 = 0.1;
+    /// Browsers and touchpads scroll by pixels rather than lines
+    const PIXELS_PER_LINE: f32 = 40.0;
+    /// Speed multipliers of the fly keys with Shift and with Ctrl
+    const FAST_FLY: f32 = 4.0;
+    const SLOW_FLY: f32 = 0.25;
+
+    pub fn dragging(&self) -> Option<DragMode> {
+        self.drag
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn mouse_input(&mut self, button: MouseButton, state: ElementState) {
+        self.drag = match (state, button) {
+            (ElementState::Pressed, MouseButton::Left) if self.modifiers.shift() => {
+                Some(DragMode::Pan)
+            }
+            (ElementState::Pressed, MouseButton::Left) if self.modifiers.ctrl() => {
+                Some(DragMode::Zoom)
+            }
+            (ElementState::Pressed, MouseButton::Left) => Some(DragMode::Orbit),
+            (ElementState::Pressed, MouseButton::Right | MouseButton::Middle) => {
+                Some(DragMode::Pan)
+            }
+            (ElementState::Released, _) => None,
+            _ => self.drag,
+        };
+    }
+
+    pub fn is_pointer_locked(&self) -> bool {
+        self.pointer_locked
+    }
+
+    /// Switches dragging between following the cursor and following the
+    /// raw mouse motion, for when the cursor is locked in place.
+    pub fn set_pointer_locked(&mut self, locked: bool) {
+        self.pointer_locked = locked;
+        self.cursor = None;
+    }
+
+    pub fn cursor_moved(&mut self, position: PhysicalPosition<f64>) {
+        let position = glam::Vec2::new(position.x as f32, position.y as f32);
+        let delta = self
+            .cursor
+            .map_or(glam::Vec2::ZERO, |cursor| position - cursor);
+        self.cursor = Some(position);
+
+        if !self.pointer_locked {
+            self.drag_by(delta);
+        }
+    }
+
+    /// Raw mouse motion, which keeps coming while the pointer is locked.
+    pub fn mouse_motion(&mut self, delta: (f64, f64)) {
+        if self.pointer_locked && self.drag == Some(DragMode::Orbit) {
+            let delta = glam::Vec2::new(delta.0 as f32, delta.1 as f32);
+            self.push(CameraMotion {
+                orbit: delta * Self::LOOK_SPEED * self.settings.orbit_sensitivity,
+                ..Default::default()
+            });
+        }
+    }
+
+    fn drag_by(&mut self, delta: glam::Vec2) {
+        let motion = match self.drag {
+            Some(DragMode::Orbit) => CameraMotion {
+                orbit: delta * Self::ORBIT_SPEED * self.settings.orbit_sensitivity,
+                ..Default::default()
+            },
+            Some(DragMode::Pan) => CameraMotion {
+                pan: delta * self.settings.pan_sensitivity,
+                ..Default::default()
+            },
+            Some(DragMode::Zoom) => CameraMotion {
+                zoom: -delta.y * Self::ZOOM_SPEED * self.settings.zoom_sensitivity,
+                ..Default::default()
+            },
+            None => return,
+        };
+
+        self.push(motion);
+    }
+
+    /// Forgets the cursor position, so that it doesn't jump on re-entering.
+    pub fn cursor_left(&mut self) {
+        self.cursor = None;
+    }
+
+    pub fn mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(position) => position.y as f32 / Self::PIXELS_PER_LINE,
+        };
+
+        self.push(CameraMotion {
+            zoom: lines * Self::WHEEL_ZOOM_SPEED * self.settings.zoom_sensitivity,
+            ..Default::default()
+        });
+    }
+
+    /// Tracks which fly keys are held down.
+    pub fn keyboard_input(&mut self, key: VirtualKeyCode, state: ElementState) {
+        if !FLY_KEYS.iter().any(|&(fly_key, _)| fly_key == key) {
+            return;
+        }
+
+        self.fly_keys.retain(|&held| held != key);
+        if state == ElementState::Pressed {
+            self.fly_keys.push(key);
+        }
+    }
+
+    /// Lets go of all keys and buttons, as their releases go unnoticed while
+    /// the window is out of focus.
+    pub fn release_all(&mut self) {
+        self.fly_keys.clear();
+        self.drag = None;
+    }
+
+    /// Direction of the held fly keys, scaled by the speed modifiers.
+    fn fly_velocity(&self) -> glam::Vec3 {
+        let direction = FLY_KEYS
+            .iter()
+            .filter(|(key, _)| self.fly_keys.contains(key))
+            .map(|&(_, direction)| direction)
+            .sum::<glam::Vec3>()
+            .normalize_or_zero();
+
+        let multiplier = if self.modifiers.shift() {
+            Self::FAST_FLY
+        } else if self.modifiers.ctrl() {
+            Self::SLOW_FLY
+        } else {
+            1.0
+        };
+
+        direction * multiplier
+    }
+
+    fn push(&mut self, motion: CameraMotion) {
+        self.pending = CameraMotion {
+            orbit: self.pending.orbit + motion.orbit,
+            pan: self.pending.pan + motion.pan,
+            zoom: self.pending.zoom + motion.zoom,
+            fly: self.pending.fly + motion.fly,
+        };
+    }
+
+    /// Whether there is motion left to apply. Held fly keys only count while
+    /// `flying`, so that they don't keep redrawing the orbit camera.
+    pub fn is_moving(&self, flying: bool) -> bool {
+        self.pending != CameraMotion::default()
+            || (flying && self.fly_velocity() != glam::Vec3::ZERO)
+    }
+
+    /// Takes the part of the pending motion due after `delta_time` seconds,
+    /// plus the flight of the held fly keys while `flying`, or `None` once the
+    /// camera has come to rest.
+    pub fn update(&mut self, delta_time: f32, flying: bool) -> Option<CameraMotion> {
+        if !self.is_moving(flying) {
+            return None;
+        }
+
+        let mut motion = self.take_pending(delta_time);
+        if flying {
+            motion.fly += self.fly_velocity() * delta_time;
+        }
+
+        Some(motion)
+    }
+
+    fn take_pending(&mut self, delta_time: f32) -> CameraMotion {
+        // Exponential decay of the pending motion, independent of the frame rate
+        let fraction = if self.settings.damping > 0.0 {
+            1.0 - (-delta_time / self.settings.damping).exp()
+        } else {
+            1.0
+        };
+
+        let remaining = self.pending.scale(1.0 - fraction);
+        // Finish off what is too small to see, rather than decaying forever
+        if remaining.is_negligible() {
+            std::mem::take(&mut self.pending)
+        } else {
+            let motion = self.pending.scale(fraction);
+            self.pending = remaining;
+            motion
+        }
+    }
+}
@@ -0,0 +1,88 @@
+/// How view depth maps to the depth buffer, shared by the projections, the
+/// depth buffer and every pass testing against it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DepthMode {
+    /// The near plane at depth 0 and the far plane at 1
+    #[default]
+    Standard,
+    /// The near plane at depth 1 and an infinitely far plane at 0. Floats
+    /// are most precise near 0, which evens out the precision over the
+    /// distance, so large scenes neither z-fight nor get clipped.
+    Reversed,
+}
+
+impl DepthMode {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// Passes fragments closer to the eye than the depth buffer.
+    pub fn compare(self) -> wgpu::CompareFunction {
+        match self {
+            DepthMode::Standard => wgpu::CompareFunction::Less,
+            DepthMode::Reversed => wgpu::CompareFunction::Greater,
+        }
+    }
+
+    /// The depth of the far plane, which the depth buffer is cleared to.
+    pub fn clear_value(self) -> f32 {
+        match self {
+            DepthMode::Standard => 1.0,
+            DepthMode::Reversed => 0.0,
+        }
+    }
+
+    pub fn depth_stencil_state(self) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: Self::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: self.compare(),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+
+    /// A perspective projection, which reaches to infinity if `z_far` is
+    /// `None`, and always when reversed.
+    pub fn perspective(
+        self,
+        fov_y_radians: f32,
+        aspect_ratio: f32,
+        z_near: f32,
+        z_far: Option<f32>,
+    ) -> glam::Mat4 {
+        match (self, z_far) {
+            (DepthMode::Standard, Some(z_far)) => {
+                glam::Mat4::perspective_lh(fov_y_radians, aspect_ratio, z_near, z_far)
+            }
+            (DepthMode::Standard, None) => {
+                glam::Mat4::perspective_infinite_lh(fov_y_radians, aspect_ratio, z_near)
+            }
+            (DepthMode::Reversed, _) => {
+                glam::Mat4::perspective_infinite_reverse_lh(fov_y_radians, aspect_ratio, z_near)
+            }
+        }
+    }
+
+    /// An orthographic projection centered on the viewing direction.
+    pub fn orthographic(
+        self,
+        half_width: f32,
+        half_height: f32,
+        z_near: f32,
+        z_far: f32,
+    ) -> glam::Mat4 {
+        // Without a perspective divide, swapping the planes is all it takes
+        let (z_near, z_far) = match self {
+            DepthMode::Standard => (z_near, z_far),
+            DepthMode::Reversed => (z_far, z_near),
+        };
+
+        glam::Mat4::orthographic_lh(
+            -half_width,
+            half_width,
+            -half_height,
+            half_height,
+            z_near,
+            z_far,
+        )
+    }
+}
@@ -4,8 +4,12 @@ use crate::resources;
 pub struct Entity {
     pub name: Option<String>,
     pub mesh_index: Option<usize>,
+    pub camera_index: Option<usize>,
     pub children: Vec<usize>,
     pub transform: glam::Mat4,
+    /// Local transforms of the mesh instances, if the mesh is drawn more
+    /// than once at this entity
+    pub instances: Option<Vec<glam::Mat4>>,
 }
 
 impl From<resources::Node> for Entity {
@@ -13,8 +17,10 @@ impl From<resources::Node> for Entity {
         Self {
             name: node.name,
             mesh_index: node.mesh_index,
+            camera_index: node.camera_index,
             children: node.children,
             transform: node.transform,
+            instances: node.instances,
         }
     }
 }
@@ -1,3 +1,8 @@
+use anyhow::anyhow;
+
+use crate::depth::DepthMode;
+use crate::Result;
+
 pub trait DeviceExt {
     fn create_depth_texture(&self, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView;
 }
@@ -14,9 +19,9 @@ impl DeviceExt for wgpu::Device {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
+            format: DepthMode::FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[wgpu::TextureFormat::Depth32Float],
+            view_formats: &[DepthMode::FORMAT],
         });
 
         depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
@@ -24,21 +29,70 @@ impl DeviceExt for wgpu::Device {
 }
 
 pub trait RgbaImageExt {
-    fn from_gltf_image(image: gltf::image::Data) -> Option<image::RgbaImage>;
+    fn from_gltf_image(image: gltf::image::Data) -> Result<image::RgbaImage>;
 }
 
 impl RgbaImageExt for image::RgbaImage {
-    fn from_gltf_image(image: gltf::image::Data) -> Option<image::RgbaImage> {
+    fn from_gltf_image(image: gltf::image::Data) -> Result<image::RgbaImage> {
         use gltf::image::Format;
-        use image::buffer::ConvertBuffer;
-        Some(match image.format {
-            Format::R8G8B8A8 => {
-                image::RgbaImage::from_raw(image.width, image.height, image.pixels)?
+        use image::{DynamicImage, ImageBuffer};
+
+        let gltf::image::Data {
+            format,
+            width,
+            height,
+            pixels,
+        } = image;
+
+        // 16-bit and float formats are stored as native-endian bytes, so reinterpret
+        // them as their channel type first. Everything is converted to RGBA8 below,
+        // which is what `Texture` uploads on every platform.
+        let image = match format {
+            Format::R8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+            }
+            Format::R8G8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8)
             }
             Format::R8G8B8 => {
-                image::RgbImage::from_raw(image.width, image.height, image.pixels)?.convert()
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+            }
+            Format::R8G8B8A8 => {
+                ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
             }
-            _ => unimplemented!("Image format not yet implemented: {:?}", image.format),
+            Format::R16 => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageLuma16)
+            }
+            Format::R16G16 => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageLumaA16)
+            }
+            Format::R16G16B16 => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageRgb16)
+            }
+            Format::R16G16B16A16 => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageRgba16)
+            }
+            Format::R32G32B32FLOAT => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageRgb32F)
+            }
+            Format::R32G32B32A32FLOAT => {
+                ImageBuffer::from_raw(width, height, bytemuck::pod_collect_to_vec(&pixels))
+                    .map(DynamicImage::ImageRgba32F)
+            }
+        };
+
+        image.map(DynamicImage::into_rgba8).ok_or_else(|| {
+            anyhow!(
+                "Image data does not match its size: {:?}, {}x{}",
+                format,
+                width,
+                height
+            )
         })
     }
 }
@@ -4,15 +4,22 @@ pub mod renderer;
 
 pub mod resources;
 
+pub mod bookmark;
+pub mod bounds;
 pub mod camera;
+pub mod controls;
+pub mod depth;
 pub mod entity;
 pub mod material;
 pub mod mesh;
 pub mod model;
 pub mod scene;
+pub mod scene_color;
 pub mod texture;
+pub mod turntable;
 pub mod uniform;
 pub mod vertex;
+pub mod viewport;
 
 mod ext;
 
@@ -24,4 +31,4 @@ pub mod wasm;
 pub use anyhow::Result;
 pub use model::Model;
 pub use renderer::Renderer;
-pub use resources::Resources;
+pub use resources::{LoadOptions, Resources};
@@ -1,6 +1,24 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::anyhow;
 use anyhow::Result;
 use clap::Parser;
 use nugget::app;
+#[cfg(not(target_arch = "wasm32"))]
+use nugget::bookmark;
+use nugget::camera::OrbitMode;
+use nugget::controls::ControlSettings;
+use nugget::depth::DepthMode;
+use nugget::resources::{Metadata, NormalsMode};
+use nugget::scene::Scene;
+#[cfg(not(target_arch = "wasm32"))]
+use nugget::turntable::{AnimationFormat, Turntable};
+use nugget::viewport::Layout;
+#[cfg(not(target_arch = "wasm32"))]
+use nugget::Renderer;
+use nugget::{LoadOptions, Resources};
 use winit::event_loop::EventLoopBuilder;
 
 /// Who hates nuggets?
@@ -12,6 +30,91 @@ struct Args {
     /// Whether to render in wireframe mode
     #[arg(short, long)]
     line: bool,
+    /// Generate smooth instead of flat normals for primitives without them,
+    /// splitting edges sharper than the given angle in degrees
+    #[arg(long, value_name = "ANGLE")]
+    smooth_normals: Option<f32>,
+    /// Material variant (`KHR_materials_variants`) to show, press V to cycle
+    #[arg(long, value_name = "NAME")]
+    variant: Option<String>,
+    /// glTF camera to view through, by name or index, press C to cycle
+    #[arg(long, value_name = "NAME")]
+    camera: Option<String>,
+    /// Bookmark to start from, out of the `.bookmarks.json` file next to the
+    /// model. Press 1 to 9 to go to the bookmark of that name, Ctrl+1 to 9
+    /// to save one.
+    #[arg(long, value_name = "NAME")]
+    bookmark: Option<String>,
+    /// Use a reversed, infinitely far depth range, for large scenes
+    #[arg(long)]
+    reverse_z: bool,
+    /// Multiplier on how fast dragging with the left mouse button orbits
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    orbit_sensitivity: f32,
+    /// Multiplier on how fast dragging with the right mouse button pans
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    pan_sensitivity: f32,
+    /// Multiplier on how fast the mouse wheel zooms
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    zoom_sensitivity: f32,
+    /// How dragging orbits the camera: `turntable` keeps the horizon level,
+    /// `trackball` turns freely. Press T to switch.
+    #[arg(long, value_name = "MODE", default_value = "turntable")]
+    orbit_mode: OrbitMode,
+    /// Seconds the camera takes to follow the mouse, 0 to follow at once
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.08)]
+    damping: f32,
+    /// How the window is split: `single`, `quad` for a perspective and three
+    /// orthographic views, or `stereo` for side-by-side eyes. Press L to cycle.
+    #[arg(long, value_name = "LAYOUT", default_value = "single")]
+    layout: Layout,
+    /// Distance between the eyes of the stereo layout, in the model's units
+    #[arg(long, value_name = "DISTANCE", default_value_t = Scene::DEFAULT_EYE_SEPARATION)]
+    eye_separation: f32,
+    /// Print the material variants of the model and exit
+    #[arg(long)]
+    list_variants: bool,
+    /// Print the scenes, nodes, cameras, meshes and materials of the model
+    /// with their extras and `KHR_xmp_json_ld` metadata, and exit
+    #[arg(long)]
+    info: bool,
+    /// Render a turn around the model offscreen into the given directory, as
+    /// numbered PNGs plus an animation, and exit. The turn starts from the
+    /// `--bookmark` if given. glTF animations aren't played, so animated
+    /// models turn in their rest pose.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "DIRECTORY")]
+    turntable: Option<PathBuf>,
+    /// Frames of the turntable
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 120,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    frames: u32,
+    /// Frames per second of the turntable animation
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(
+        long,
+        value_name = "FPS",
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u32).range(1..=Turntable::MAX_FRAME_RATE as i64)
+    )]
+    frame_rate: u32,
+    /// Format of the turntable animation: `gif` or `apng`
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "FORMAT", default_value = "gif")]
+    animation_format: AnimationFormat,
+    /// Width of the turntable images
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "PIXELS", default_value_t = 800)]
+    width: u32,
+    /// Height of the turntable images
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "PIXELS", default_value_t = 800)]
+    height: u32,
 }
 
 pub fn main() -> Result<()> {
@@ -19,19 +122,164 @@ pub fn main() -> Result<()> {
 
     let args = Args::parse();
 
+    let normals = match args.smooth_normals {
+        Some(angle_threshold) => NormalsMode::Smooth { angle_threshold },
+        None => NormalsMode::Flat,
+    };
+    let options = LoadOptions { normals };
+
+    if args.list_variants {
+        let resources = pollster::block_on(Resources::load_gltf_with_options(&args.path, options))?;
+        for variant in resources.variants {
+            println!("{variant}");
+        }
+        return Ok(());
+    }
+
+    if args.info {
+        let resources = pollster::block_on(Resources::load_gltf_with_options(&args.path, options))?;
+        print_info(&resources);
+        return Ok(());
+    }
+
+    let depth_mode = if args.reverse_z {
+        DepthMode::Reversed
+    } else {
+        DepthMode::Standard
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(directory) = &args.turntable {
+        return export_turntable(&args, directory, options, depth_mode);
+    }
+
     let event_loop = EventLoopBuilder::<app::AppEvent>::with_user_event().build();
     let window = winit::window::WindowBuilder::new()
         .with_title("nugget")
         .build(&event_loop)?;
 
-    event_loop
-        .create_proxy()
-        .send_event(app::AppEvent::LoadResourcesRequest { path: args.path })?;
+    let proxy = event_loop.create_proxy();
+
+    // Applied once the model is loaded
+    if let Some(variant) = args.variant {
+        proxy.send_event(app::AppEvent::SetVariantRequest(Some(variant)))?;
+    }
+    if let Some(camera) = args.camera {
+        proxy.send_event(app::AppEvent::SetCameraRequest(Some(camera)))?;
+    }
+    if let Some(bookmark) = args.bookmark {
+        proxy.send_event(app::AppEvent::GoToBookmarkRequest(bookmark))?;
+    }
+    proxy.send_event(app::AppEvent::SetControlsRequest(ControlSettings {
+        orbit_sensitivity: args.orbit_sensitivity,
+        pan_sensitivity: args.pan_sensitivity,
+        zoom_sensitivity: args.zoom_sensitivity,
+        damping: args.damping,
+    }))?;
+    proxy.send_event(app::AppEvent::SetOrbitModeRequest(args.orbit_mode))?;
+    proxy.send_event(app::AppEvent::SetEyeSeparationRequest(args.eye_separation))?;
+    proxy.send_event(app::AppEvent::SetLayoutRequest(args.layout))?;
 
-    pollster::block_on(nugget::app::run(window, event_loop, args.line)).map_err(|error| {
-        tracing::error!(?error);
-        error
+    proxy.send_event(app::AppEvent::LoadResourcesRequest {
+        path: args.path,
+        options,
     })?;
 
+    pollster::block_on(nugget::app::run(window, event_loop, args.line, depth_mode)).map_err(
+        |error| {
+            tracing::error!(?error);
+            error
+        },
+    )?;
+
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn export_turntable(
+    args: &Args,
+    directory: &Path,
+    options: LoadOptions,
+    depth_mode: DepthMode,
+) -> Result<()> {
+    let resources = pollster::block_on(Resources::load_gltf_with_options(&args.path, options))?;
+    if !resources.animations.is_empty() {
+        tracing::warn!(
+            animations = ?resources.animations,
+            "Animations aren't played, the turntable shows the model in its rest pose"
+        );
+    }
+
+    let mut renderer = pollster::block_on(Renderer::new_headless(
+        args.width,
+        args.height,
+        args.line,
+        depth_mode,
+    ))?;
+    renderer.load_resources(resources);
+    renderer.set_eye_separation(args.eye_separation);
+    renderer.set_layout(args.layout);
+
+    if let Some(variant) = &args.variant {
+        if !renderer.set_variant(Some(variant)) {
+            return Err(anyhow!("Model has no material variant {variant:?}"));
+        }
+    }
+    if let Some(name) = &args.bookmark {
+        renderer.set_bookmarks(bookmark::load(Path::new(&args.path))?);
+        if !renderer.restore_bookmark(name) {
+            return Err(anyhow!("Model has no bookmark {name:?}"));
+        }
+    }
+
+    let turntable = Turntable {
+        frames: args.frames,
+        frame_rate: args.frame_rate,
+        format: args.animation_format,
+    };
+    pollster::block_on(turntable.export(&mut renderer, directory))?;
+    tracing::info!(?directory, frames = args.frames, "Exported turntable");
+
     Ok(())
 }
+
+fn print_info(resources: &Resources) {
+    let print_object = |kind: &str, index: usize, name: &Option<String>, metadata: &Metadata| {
+        match name {
+            Some(name) => println!("{kind} {index} \"{name}\""),
+            None => println!("{kind} {index}"),
+        }
+        if let Some(extras) = metadata.extras() {
+            println!("  extras: {extras}");
+        }
+        if let Some(packet) = resources.xmp_packet(metadata) {
+            println!("  xmp: {packet}");
+        }
+    };
+
+    for (index, scene) in resources.scenes.iter().enumerate() {
+        print_object("Scene", index, &scene.name, &scene.metadata);
+    }
+    for (index, node) in resources.nodes.iter().enumerate() {
+        print_object("Node", index, &node.name, &node.metadata);
+    }
+    for (index, camera) in resources.cameras.iter().enumerate() {
+        match &camera.name {
+            Some(name) => println!("Camera {index} \"{name}\""),
+            None => println!("Camera {index}"),
+        }
+        println!("  projection: {:?}", camera.projection);
+    }
+    for (index, mesh) in resources.meshes.iter().enumerate() {
+        print_object("Mesh", index, &mesh.name, &mesh.metadata);
+    }
+    for (index, material) in resources.materials.iter().enumerate() {
+        print_object("Material", index, &material.name, &material.metadata);
+    }
+    for variant in &resources.variants {
+        println!("Variant \"{variant}\"");
+    }
+    for (index, packet) in resources.xmp_packets.iter().enumerate() {
+        println!("XMP packet {index}: {packet}");
+    }
+}
@@ -1,12 +1,20 @@
 use wgpu::util::DeviceExt;
 
-use crate::{resources, texture::Texture, uniform::MaterialFactorsBinding};
+use crate::{
+    resources,
+    texture::Texture,
+    uniform::{MaterialFactorsBinding, TextureInfoBinding},
+};
 
 #[derive(Debug)]
 pub struct Material {
     pub name: Option<String>,
+    /// Selects the pipeline primitives using this material are drawn with
+    pub shading_model: resources::ShadingModel,
     pub factors: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
+    /// Drawn after the opaque scene color has been captured
+    pub transmissive: bool,
 }
 
 impl Material {
@@ -27,60 +35,45 @@ impl Material {
                     },
                     count: None,
                 },
-                // base color texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                // base color sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-                // normal texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                // normal sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 4,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-                // metallic roughness texture
-                wgpu::BindGroupLayoutEntry {
-                    binding: 5,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                // metallic roughness sampler
-                wgpu::BindGroupLayoutEntry {
-                    binding: 6,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
+                // base color texture and sampler
+                texture_layout_entry(1),
+                sampler_layout_entry(2),
+                // normal texture and sampler
+                texture_layout_entry(3),
+                sampler_layout_entry(4),
+                // metallic roughness texture and sampler
+                texture_layout_entry(5),
+                sampler_layout_entry(6),
+                // emissive texture and sampler
+                texture_layout_entry(7),
+                sampler_layout_entry(8),
+                // clearcoat texture and sampler
+                texture_layout_entry(9),
+                sampler_layout_entry(10),
+                // clearcoat roughness texture and sampler
+                texture_layout_entry(11),
+                sampler_layout_entry(12),
+                // clearcoat normal texture and sampler
+                texture_layout_entry(13),
+                sampler_layout_entry(14),
+                // sheen color texture and sampler
+                texture_layout_entry(15),
+                sampler_layout_entry(16),
+                // sheen roughness texture and sampler
+                texture_layout_entry(17),
+                sampler_layout_entry(18),
+                // transmission texture and sampler
+                texture_layout_entry(19),
+                sampler_layout_entry(20),
+                // thickness texture and sampler
+                texture_layout_entry(21),
+                sampler_layout_entry(22),
+                // specular texture and sampler
+                texture_layout_entry(23),
+                sampler_layout_entry(24),
+                // specular color texture and sampler
+                texture_layout_entry(25),
+                sampler_layout_entry(26),
             ],
         };
 
@@ -91,10 +84,51 @@ impl Material {
         queue: &wgpu::Queue,
         bind_group_layout: &wgpu::BindGroupLayout,
     ) -> Self {
+        let clearcoat = material.clearcoat;
+        let sheen = material.sheen;
+        let transmission = material.transmission;
+        let volume = material.volume;
+        let specular = material.specular;
+
         let factors = MaterialFactorsBinding {
             base_color_factor: material.base_color_factor,
             metallic_factor: material.metallic_factor,
             roughness_factor: material.roughness_factor,
+            ior: material.ior,
+            specular_factor: specular.map_or(1.0, |specular| specular.factor),
+            emissive_factor: material.emissive_factor,
+            emissive_strength: material.emissive_strength,
+            specular_color_factor: specular.map_or([1.0; 3], |specular| specular.color_factor),
+            clearcoat_factor: clearcoat.map_or(0.0, |clearcoat| clearcoat.factor),
+            attenuation_color: volume.map_or([1.0; 3], |volume| volume.attenuation_color),
+            attenuation_distance: volume
+                .map(|volume| volume.attenuation_distance)
+                .filter(|distance| distance.is_finite())
+                .unwrap_or(0.0),
+            sheen_color_factor: sheen.map_or([0.0; 3], |sheen| sheen.color_factor),
+            sheen_roughness_factor: sheen.map_or(0.0, |sheen| sheen.roughness_factor),
+            clearcoat_roughness_factor: clearcoat
+                .map_or(0.0, |clearcoat| clearcoat.roughness_factor),
+            transmission_factor: transmission.map_or(0.0, |transmission| transmission.factor),
+            thickness_factor: volume.map_or(0.0, |volume| volume.thickness_factor),
+            _padding: 0,
+            base_color_texture: texture_info_binding(material.base_color_texture),
+            normal_texture: texture_info_binding(material.normal_texture),
+            metallic_roughness_texture: texture_info_binding(material.metallic_roughness_texture),
+            emissive_texture: texture_info_binding(material.emissive_texture),
+            clearcoat_texture: texture_info_binding(clearcoat.and_then(|c| c.texture)),
+            clearcoat_roughness_texture: texture_info_binding(
+                clearcoat.and_then(|c| c.roughness_texture),
+            ),
+            clearcoat_normal_texture: texture_info_binding(
+                clearcoat.and_then(|c| c.normal_texture),
+            ),
+            sheen_color_texture: texture_info_binding(sheen.and_then(|s| s.color_texture)),
+            sheen_roughness_texture: texture_info_binding(sheen.and_then(|s| s.roughness_texture)),
+            transmission_texture: texture_info_binding(transmission.and_then(|t| t.texture)),
+            thickness_texture: texture_info_binding(volume.and_then(|v| v.thickness_texture)),
+            specular_texture: texture_info_binding(specular.and_then(|s| s.texture)),
+            specular_color_texture: texture_info_binding(specular.and_then(|s| s.color_texture)),
         };
 
         let factors_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -103,66 +137,135 @@ impl Material {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let texture = |info: Option<resources::TextureInfo>| {
+            info.map(|info| &textures[info.index])
+                .unwrap_or_else(|| Texture::default_white_texture(device, queue))
+        };
+
         let base_color_texture = material
-            .base_color_texture_index
-            .map(|i| &textures[i])
+            .base_color_texture
+            .map(|info| &textures[info.index])
             .unwrap_or_else(|| Texture::default_base_color_texture(device, queue));
         let normal_texture = material
-            .normal_texture_index
-            .map(|i| &textures[i])
+            .normal_texture
+            .map(|info| &textures[info.index])
             .unwrap_or_else(|| Texture::default_normal_texture(device, queue));
         let metallic_roughness_texture = material
-            .metallic_roughness_texture_index
-            .map(|i| &textures[i])
+            .metallic_roughness_texture
+            .map(|info| &textures[info.index])
             .unwrap_or_else(|| Texture::default_metallic_roughness_texture(device, queue));
+        let clearcoat_normal_texture = clearcoat
+            .and_then(|clearcoat| clearcoat.normal_texture)
+            .map(|info| &textures[info.index])
+            .unwrap_or_else(|| Texture::default_normal_texture(device, queue));
+
+        // In binding order, color textures are sampled as sRGB
+        let slots = [
+            (base_color_texture, wgpu::TextureFormat::Rgba8UnormSrgb),
+            (normal_texture, wgpu::TextureFormat::Rgba8Unorm),
+            (metallic_roughness_texture, wgpu::TextureFormat::Rgba8Unorm),
+            (
+                texture(material.emissive_texture),
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ),
+            (
+                texture(clearcoat.and_then(|c| c.texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                texture(clearcoat.and_then(|c| c.roughness_texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (clearcoat_normal_texture, wgpu::TextureFormat::Rgba8Unorm),
+            (
+                texture(sheen.and_then(|s| s.color_texture)),
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ),
+            (
+                texture(sheen.and_then(|s| s.roughness_texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                texture(transmission.and_then(|t| t.texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                texture(volume.and_then(|v| v.thickness_texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                texture(specular.and_then(|s| s.texture)),
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                texture(specular.and_then(|s| s.color_texture)),
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ),
+        ];
+
+        let views: Vec<wgpu::TextureView> = slots
+            .iter()
+            .map(|(texture, format)| texture.create_view(*format))
+            .collect();
+
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(factors_buffer.as_entire_buffer_binding()),
+        }];
+        for (slot, ((texture, _), view)) in slots.iter().zip(&views).enumerate() {
+            let binding = 1 + 2 * slot as u32;
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: binding + 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Material Bind Group"),
             layout: bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        factors_buffer.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(
-                        &base_color_texture.create_view(wgpu::TextureFormat::Rgba8UnormSrgb),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&base_color_texture.sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::TextureView(
-                        &normal_texture.create_view(wgpu::TextureFormat::Rgba8Unorm),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 5,
-                    resource: wgpu::BindingResource::TextureView(
-                        &metallic_roughness_texture.create_view(wgpu::TextureFormat::Rgba8Unorm),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 6,
-                    resource: wgpu::BindingResource::Sampler(&metallic_roughness_texture.sampler),
-                },
-            ],
+            entries: &entries,
         });
 
+        let transmissive = transmission.is_some();
+
         Material {
             name: material.name,
+            shading_model: material.shading_model,
             factors: factors_buffer,
             bind_group,
+            transmissive,
         }
     }
 }
+
+fn texture_info_binding(texture: Option<resources::TextureInfo>) -> TextureInfoBinding {
+    texture.map_or_else(TextureInfoBinding::default, |info| {
+        TextureInfoBinding::new(info.tex_coord, info.transform.matrix())
+    })
+}
+
+const fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+const fn sampler_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
@@ -1,10 +1,21 @@
-use crate::resources;
+use std::collections::HashMap;
+
+use crate::bounds::Aabb;
+use crate::resources::{self, QuantizedAttribute};
+use crate::vertex::{VertexAttribute, VertexFormats};
+use gltf::{accessor::DataType, mesh::Mode};
 use wgpu::util::DeviceExt;
 
 #[derive(Debug)]
 pub struct Mesh {
     pub name: Option<String>,
     pub primitives: Vec<Primitive>,
+    /// Non-normalized integer positions are read as normalized ones, which
+    /// this scale, folded into the node transform, undoes
+    pub position_scale: f32,
+    /// Bounds of all primitives in the mesh's own space, from the accessor
+    /// `min` and `max`. They need the node transforms but not `position_scale`.
+    pub bounds: Aabb,
 }
 
 #[derive(Debug)]
@@ -13,12 +24,36 @@ pub struct Primitive {
     pub tex_coords: wgpu::Buffer,
     pub normals: wgpu::Buffer,
     pub tangents: wgpu::Buffer,
-    pub indices: wgpu::Buffer,
-    pub material_index: usize,
+    pub colors: wgpu::Buffer,
+    pub tex_coords_1: wgpu::Buffer,
+    pub indices: Option<wgpu::Buffer>,
+    /// Number of indices, or of vertices for a non-indexed primitive
+    pub count: u32,
+    pub topology: wgpu::PrimitiveTopology,
+    pub vertex_formats: VertexFormats,
+    /// The material of the active variant
+    pub material_index: Option<usize>,
+    pub default_material_index: Option<usize>,
+    /// `KHR_materials_variants` mappings from variant index to material index
+    pub material_variants: HashMap<usize, usize>,
 }
 
 impl Mesh {
     pub fn new(mesh: &resources::Mesh, device: &wgpu::Device) -> Mesh {
+        // Primitives share the node transform, so quantized positions stay
+        // compact only if they all need the same scale
+        let scales = mesh.primitives.iter().map(|primitive| {
+            primitive
+                .quantized
+                .positions
+                .as_ref()
+                .map_or(1.0, QuantizedAttribute::scale)
+        });
+        let position_scale = match scales.clone().next() {
+            Some(first) if scales.clone().all(|scale| scale == first) => first,
+            _ => 1.0,
+        };
+
         let mut primitives = vec![];
         for (index, primitive) in mesh.primitives.iter().enumerate() {
             let debug_label = format!(
@@ -27,7 +62,7 @@ impl Mesh {
                 index
             );
 
-            let primitive = Primitive::new(primitive, &debug_label, device);
+            let primitive = Primitive::new(primitive, position_scale, &debug_label, device);
 
             primitives.push(primitive);
         }
@@ -35,6 +70,8 @@ impl Mesh {
         Mesh {
             name: mesh.name.clone(),
             primitives,
+            position_scale,
+            bounds: mesh.bounds,
         }
     }
 }
@@ -42,26 +79,38 @@ impl Mesh {
 impl Primitive {
     pub fn new(
         primitive: &resources::Primitive,
+        position_scale: f32,
         debug_label: &str,
         device: &wgpu::Device,
     ) -> Primitive {
-        let positions = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Position Buffer {}", debug_label)),
-            contents: bytemuck::cast_slice(&primitive.positions),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let quantized = &primitive.quantized;
 
-        let tex_coords = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Tex Coord Buffer {}", debug_label)),
-            contents: bytemuck::cast_slice(&primitive.tex_coords),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let (positions, position_format) = vertex_buffer(
+            &format!("Position Buffer {}", debug_label),
+            bytemuck::cast_slice(&primitive.positions),
+            quantized
+                .positions
+                .as_ref()
+                .filter(|positions| positions.scale() == position_scale),
+            VertexAttribute::Position,
+            device,
+        );
 
-        let normals = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Normal Buffer {}", debug_label)),
-            contents: bytemuck::cast_slice(&primitive.normals),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        let (tex_coords, tex_coord_format) = vertex_buffer(
+            &format!("Tex Coord Buffer {}", debug_label),
+            bytemuck::cast_slice(&primitive.tex_coords),
+            quantized.tex_coords.as_ref(),
+            VertexAttribute::TexCoord,
+            device,
+        );
+
+        let (normals, normal_format) = vertex_buffer(
+            &format!("Normal Buffer {}", debug_label),
+            bytemuck::cast_slice(&primitive.normals),
+            quantized.normals.as_ref(),
+            VertexAttribute::Normal,
+            device,
+        );
 
         let tangents = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(&format!("Tangent Buffer {}", debug_label)),
@@ -69,19 +118,109 @@ impl Primitive {
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        let indices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Index Buffer {}", debug_label)),
-            contents: bytemuck::cast_slice(&primitive.indices),
-            usage: wgpu::BufferUsages::INDEX,
+        let colors = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("Color Buffer {}", debug_label)),
+            contents: bytemuck::cast_slice(&primitive.colors),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let (tex_coords_1, tex_coord_1_format) = vertex_buffer(
+            &format!("Tex Coord 1 Buffer {}", debug_label),
+            bytemuck::cast_slice(&primitive.tex_coords_1),
+            quantized.tex_coords_1.as_ref(),
+            VertexAttribute::TexCoord1,
+            device,
+        );
+
+        let indices = primitive.indices.as_ref().map(|indices| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("Index Buffer {}", debug_label)),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            })
         });
 
+        let count = match &primitive.indices {
+            Some(indices) => indices.len(),
+            None => primitive.positions.len(),
+        } as u32;
+
         Primitive {
             material_index: primitive.material_index,
+            default_material_index: primitive.material_index,
+            material_variants: primitive.material_variants.clone(),
             positions,
             tex_coords,
             normals,
             tangents,
+            colors,
+            tex_coords_1,
             indices,
+            count,
+            topology: topology(primitive.mode),
+            vertex_formats: VertexFormats {
+                position: position_format,
+                tex_coord: tex_coord_format,
+                normal: normal_format,
+                tex_coord_1: tex_coord_1_format,
+            },
         }
     }
+
+    /// Switches to the material mapped to `variant`, or back to the default
+    /// material if the variant is `None` or has no mapping for this primitive.
+    pub fn set_variant(&mut self, variant: Option<usize>) {
+        self.material_index = variant
+            .and_then(|variant| self.material_variants.get(&variant).copied())
+            .or(self.default_material_index);
+    }
+}
+
+/// Creates a vertex buffer from quantized data if there is any, or else
+/// from the `f32` data.
+fn vertex_buffer(
+    label: &str,
+    data: &[u8],
+    quantized: Option<&QuantizedAttribute>,
+    attribute: VertexAttribute,
+    device: &wgpu::Device,
+) -> (wgpu::Buffer, wgpu::VertexFormat) {
+    let (contents, format) = match quantized {
+        Some(quantized) => (quantized.data.as_slice(), vertex_format(quantized)),
+        None => (data, attribute.format()),
+    };
+
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents,
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    (buffer, format)
+}
+
+/// Integers are always read as normalized; see `Mesh::position_scale`.
+fn vertex_format(attribute: &QuantizedAttribute) -> wgpu::VertexFormat {
+    use wgpu::VertexFormat::*;
+    match (attribute.data_type, attribute.components) {
+        (DataType::I8, _) => Snorm8x4,
+        (DataType::U8, _) => Unorm8x4,
+        (DataType::I16, 2) => Snorm16x2,
+        (DataType::I16, _) => Snorm16x4,
+        (DataType::U16, 2) => Unorm16x2,
+        (DataType::U16, _) => Unorm16x4,
+        (DataType::U32 | DataType::F32, _) => unreachable!("Floats are never quantized"),
+    }
+}
+
+fn topology(mode: Mode) -> wgpu::PrimitiveTopology {
+    match mode {
+        Mode::Points => wgpu::PrimitiveTopology::PointList,
+        Mode::Lines => wgpu::PrimitiveTopology::LineList,
+        // Line loops are closed into strips by the loader
+        Mode::LineStrip | Mode::LineLoop => wgpu::PrimitiveTopology::LineStrip,
+        // Triangle fans are unrolled into lists by the loader
+        Mode::Triangles | Mode::TriangleFan => wgpu::PrimitiveTopology::TriangleList,
+        Mode::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
+    }
 }
@@ -1,8 +1,25 @@
+use std::ops::Range;
+
+use crate::bounds::Aabb;
+use crate::camera::NodeCamera;
 use crate::entity::Entity;
 use crate::material::Material;
 use crate::mesh::Mesh;
-use crate::uniform::{EntityBinding, UniformsArray};
-use crate::vertex::VertexAttribute;
+use crate::renderer::{Pipelines, RenderPhase};
+use crate::resources;
+use crate::uniform::{ModelBinding, Uniforms};
+use crate::vertex::{InstanceIn, VertexAttribute};
+
+use wgpu::util::DeviceExt;
+
+/// All instances of a mesh, drawn together with one instanced draw call per
+/// primitive.
+#[derive(Debug)]
+pub struct Batch {
+    pub mesh_index: usize,
+    /// Range of the instances in the instance buffer
+    pub instances: Range<u32>,
+}
 
 #[derive(Debug)]
 pub struct Model {
@@ -10,7 +27,16 @@ pub struct Model {
     pub entities: Vec<Entity>,
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
-    pub uniforms: UniformsArray<EntityBinding>,
+    /// Holds the transform of the root entity
+    pub uniforms: Uniforms<ModelBinding>,
+    /// Transforms of every mesh instance, grouped by mesh
+    pub instances: wgpu::Buffer,
+    pub batches: Vec<Batch>,
+    /// World space bounds of all mesh instances
+    pub bounds: Aabb,
+    /// Names of the `KHR_materials_variants` variants
+    pub variants: Vec<String>,
+    pub cameras: Vec<NodeCamera>,
 }
 
 impl Model {
@@ -24,7 +50,7 @@ impl Model {
                 visibility: wgpu::ShaderStages::VERTEX,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: true,
+                    has_dynamic_offset: false,
                     min_binding_size: None,
                 },
                 count: None,
@@ -37,94 +63,237 @@ impl Model {
         entities: Vec<Entity>,
         root_entity: Entity,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
         layout: &wgpu::BindGroupLayout,
     ) -> Self {
-        let uniforms = UniformsArray::new(entities.len(), device, layout);
+        let uniforms = Uniforms::new(
+            ModelBinding {
+                model_matrix: root_entity.transform,
+            },
+            device,
+            layout,
+        );
+
+        let mut instances = vec![vec![]; meshes.len()];
+        collect_instances(
+            &entities,
+            &root_entity,
+            glam::Mat4::IDENTITY,
+            &mut instances,
+        );
+
+        let mut batches = vec![];
+        let mut instance_data: Vec<InstanceIn> = vec![];
+        let mut bounds = Aabb::EMPTY;
+
+        // Entities sharing a mesh, and so its primitives and their
+        // materials, are drawn as instances of one another
+        for (mesh_index, transforms) in instances.into_iter().enumerate() {
+            if transforms.is_empty() {
+                continue;
+            }
+
+            let mesh = &meshes[mesh_index];
+            let scale = glam::Mat4::from_scale(glam::Vec3::splat(mesh.position_scale));
+
+            for &transform in &transforms {
+                bounds = bounds.union(&mesh.bounds.transform(root_entity.transform * transform));
+            }
+
+            let start = instance_data.len() as u32;
+            instance_data.extend(
+                transforms
+                    .into_iter()
+                    .map(|transform| InstanceIn::from(transform * scale)),
+            );
+            let end = instance_data.len() as u32;
 
-        let model = Self {
+            batches.push(Batch {
+                mesh_index,
+                instances: start..end,
+            });
+        }
+
+        let instances = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&instance_data),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
             root_entity,
             entities,
             meshes,
             materials,
             uniforms,
+            instances,
+            batches,
+            bounds,
+            variants: vec![],
+            cameras: vec![],
+        }
+    }
+
+    pub fn with_variants(self, variants: Vec<String>) -> Self {
+        Self { variants, ..self }
+    }
+
+    /// Places the glTF cameras at the entities they are attached to.
+    pub fn with_cameras(self, cameras: &[resources::Camera]) -> Self {
+        let mut node_cameras = vec![];
+        collect_cameras(
+            &self.entities,
+            cameras,
+            &self.root_entity,
+            self.root_entity.transform,
+            &mut node_cameras,
+        );
+
+        Self {
+            cameras: node_cameras,
+            ..self
+        }
+    }
+
+    /// Switches every primitive to the material of the variant called `name`,
+    /// or back to their default materials if `name` is `None`.
+    ///
+    /// Returns `false`, leaving the materials as they are, if there is no such variant.
+    pub fn set_variant(&mut self, name: Option<&str>) -> bool {
+        let variant = match name {
+            Some(name) => match self.variants.iter().position(|variant| variant == name) {
+                Some(index) => Some(index),
+                None => return false,
+            },
+            None => None,
         };
 
-        model.calculate_uniforms(&model.root_entity, model.root_entity.transform, queue);
+        for primitive in self.meshes.iter_mut().flat_map(|mesh| &mut mesh.primitives) {
+            primitive.set_variant(variant);
+        }
 
-        model
+        true
     }
 
-    fn calculate_uniforms(
-        &self,
-        entity: &Entity,
-        parent_transform: glam::Mat4,
-        queue: &wgpu::Queue,
+    pub fn render<'a>(
+        &'a self,
+        pipelines: &'a Pipelines,
+        default_material: &'a Material,
+        phase: RenderPhase,
+        render_pass: &mut wgpu::RenderPass<'a>,
     ) {
-        for &index in &entity.children {
-            let entity = &self.entities[index];
-
-            let transform = parent_transform * entity.transform;
+        if self.batches.is_empty() {
+            return;
+        }
 
-            let data = EntityBinding { transform };
+        render_pass.set_bind_group(Model::BIND_GROUP_INDEX, &self.uniforms.bind_group, &[]);
+        render_pass.set_vertex_buffer(InstanceIn::BUFFER_INDEX, self.instances.slice(..));
 
-            self.uniforms.update(data, index, queue);
+        for batch in &self.batches {
+            let mesh = &self.meshes[batch.mesh_index];
 
-            self.calculate_uniforms(entity, transform, queue);
-        }
-    }
+            for primitive in &mesh.primitives {
+                let material = primitive
+                    .material_index
+                    .map_or(default_material, |index| &self.materials[index]);
 
-    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        self.render_impl(&self.root_entity, render_pass)
-    }
+                if material.transmissive != (phase == RenderPhase::Transmissive) {
+                    continue;
+                }
 
-    fn render_impl<'a>(&'a self, entity: &Entity, render_pass: &mut wgpu::RenderPass<'a>) {
-        for &index in &entity.children {
-            let entity = &self.entities[index];
+                render_pass.set_pipeline(
+                    &pipelines[&(
+                        material.shading_model,
+                        primitive.topology,
+                        primitive.vertex_formats,
+                    )],
+                );
+                render_pass.set_bind_group(Material::BIND_GROUP_INDEX, &material.bind_group, &[]);
 
-            if let Some(mesh_index) = entity.mesh_index {
-                render_pass.set_bind_group(
-                    Model::BIND_GROUP_INDEX,
-                    &self.uniforms.bind_group,
-                    &[self.uniforms.offset(index) as _],
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::Position.location(),
+                    primitive.positions.slice(..),
+                );
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::TexCoord.location(),
+                    primitive.tex_coords.slice(..),
+                );
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::Normal.location(),
+                    primitive.normals.slice(..),
+                );
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::Tangent.location(),
+                    primitive.tangents.slice(..),
+                );
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::Color.location(),
+                    primitive.colors.slice(..),
+                );
+                render_pass.set_vertex_buffer(
+                    VertexAttribute::TexCoord1.location(),
+                    primitive.tex_coords_1.slice(..),
                 );
 
-                let mesh = &self.meshes[mesh_index];
-
-                for primitive in &mesh.primitives {
-                    let material = &self.materials[primitive.material_index];
-                    render_pass.set_bind_group(
-                        Material::BIND_GROUP_INDEX,
-                        &material.bind_group,
-                        &[],
-                    );
-
-                    render_pass.set_vertex_buffer(
-                        VertexAttribute::Position.location(),
-                        primitive.positions.slice(..),
-                    );
-                    render_pass.set_vertex_buffer(
-                        VertexAttribute::TexCoord.location(),
-                        primitive.tex_coords.slice(..),
-                    );
-                    render_pass.set_vertex_buffer(
-                        VertexAttribute::Normal.location(),
-                        primitive.normals.slice(..),
-                    );
-                    render_pass.set_vertex_buffer(
-                        VertexAttribute::Tangent.location(),
-                        primitive.tangents.slice(..),
-                    );
-
-                    render_pass
-                        .set_index_buffer(primitive.indices.slice(..), wgpu::IndexFormat::Uint32);
-
-                    // TODO: stride?
-                    render_pass.draw_indexed(0..(primitive.indices.size() / 4) as u32, 0, 0..1);
+                match &primitive.indices {
+                    Some(indices) => {
+                        render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..primitive.count, 0, batch.instances.clone());
+                    }
+                    None => render_pass.draw(0..primitive.count, batch.instances.clone()),
                 }
             }
+        }
+    }
+}
+
+/// Collects the transform of every mesh instance relative to the root
+/// entity, grouped by mesh.
+fn collect_instances(
+    entities: &[Entity],
+    entity: &Entity,
+    parent_transform: glam::Mat4,
+    instances: &mut [Vec<glam::Mat4>],
+) {
+    for &index in &entity.children {
+        let entity = &entities[index];
+
+        let transform = parent_transform * entity.transform;
+
+        if let Some(mesh_index) = entity.mesh_index {
+            match &entity.instances {
+                Some(local_transforms) => instances[mesh_index].extend(
+                    local_transforms
+                        .iter()
+                        .map(|&local_transform| transform * local_transform),
+                ),
+                None => instances[mesh_index].push(transform),
+            }
+        }
 
-            self.render_impl(entity, render_pass);
+        collect_instances(entities, entity, transform, instances);
+    }
+}
+
+fn collect_cameras(
+    entities: &[Entity],
+    cameras: &[resources::Camera],
+    entity: &Entity,
+    parent_transform: glam::Mat4,
+    node_cameras: &mut Vec<NodeCamera>,
+) {
+    for &index in &entity.children {
+        let entity = &entities[index];
+
+        let transform = parent_transform * entity.transform;
+
+        if let Some(camera) = entity.camera_index.and_then(|index| cameras.get(index)) {
+            node_cameras.push(NodeCamera {
+                name: camera.name.clone().or_else(|| entity.name.clone()),
+                projection: camera.projection,
+                transform,
+            });
         }
+
+        collect_cameras(entities, cameras, entity, transform, node_cameras);
     }
 }
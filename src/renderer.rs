@@ -1,38 +1,76 @@
+use std::collections::HashMap;
+
 use anyhow::{anyhow, Result};
 
 use tracing::info;
 
+use crate::bookmark::Bookmark;
+use crate::camera::{OrbitMode, View};
+use crate::controls::CameraMotion;
+use crate::depth::DepthMode;
 use crate::entity::Entity;
 use crate::ext::DeviceExt;
 use crate::material::Material;
 use crate::mesh::Mesh;
 use crate::model::Model;
+use crate::resources::ShadingModel;
 use crate::scene::Scene;
+use crate::scene_color::SceneColor;
 use crate::texture::Texture;
-use crate::vertex::VertexIn;
-use crate::Resources;
+use crate::vertex::{InstanceIn, VertexFormats, VertexIn};
+use crate::viewport::Layout;
+use crate::{resources, Resources};
+
+/// Render pipelines, one for each shading model, primitive topology and set
+/// of vertex formats.
+pub type Pipelines =
+    HashMap<(ShadingModel, wgpu::PrimitiveTopology, VertexFormats), wgpu::RenderPipeline>;
+
+/// Primitives are drawn in two phases, so that transmissive materials can
+/// sample the opaque scene behind them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderPhase {
+    Opaque,
+    Transmissive,
+}
 
 pub struct BindGroupLayouts {
     pub scene: wgpu::BindGroupLayout,
     pub model: wgpu::BindGroupLayout,
     pub material: wgpu::BindGroupLayout,
+    pub scene_color: wgpu::BindGroupLayout,
 }
 
 pub struct Renderer {
     pub adapter: wgpu::Adapter,
-    pub surface: wgpu::Surface,
+    /// The window's surface, or `None` for a renderer that only captures
+    /// images
+    pub surface: Option<wgpu::Surface>,
     pub config: wgpu::SurfaceConfiguration,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub shader: wgpu::ShaderModule,
-    pub pipeline: wgpu::RenderPipeline,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    pub pipelines: Pipelines,
+    /// Whether to draw in wireframe
+    pub line: bool,
+    /// Used by primitives without a material
+    pub default_material: Material,
+    pub depth_mode: DepthMode,
     pub depth_texture: wgpu::TextureView,
+    pub scene_color: SceneColor,
     pub bind_group_layouts: BindGroupLayouts,
     pub scene: Scene,
 }
 
 impl Renderer {
-    pub async fn new<W>(window: &W, width: u32, height: u32, line: bool) -> Result<Renderer>
+    pub async fn new<W>(
+        window: &W,
+        width: u32,
+        height: u32,
+        line: bool,
+        depth_mode: DepthMode,
+    ) -> Result<Renderer>
     where
         W: raw_window_handle::HasRawWindowHandle + raw_window_handle::HasRawDisplayHandle,
     {
@@ -42,18 +80,7 @@ impl Renderer {
         // Surface: handle to a presentable surface.
         let surface = unsafe { instance.create_surface(&window)? };
 
-        // An adapter identifies an implementation of WebGPU on the system.
-        let adapter_options = wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface),
-        };
-        let adapter = instance
-            .request_adapter(&adapter_options)
-            .await
-            .ok_or_else(|| anyhow!("Failed to find an appropriate GPU adapter"))?;
-
-        info!("Supported features: {:?}", adapter.features());
+        let adapter = Self::request_adapter(&instance, Some(&surface)).await?;
 
         let mut config = surface
             .get_default_config(&adapter, width, height)
@@ -72,6 +99,61 @@ impl Renderer {
             wgpu::TextureFormat::Bgra8UnormSrgb,
         ];
 
+        Self::with_surface(adapter, Some(surface), config, line, depth_mode).await
+    }
+
+    /// A renderer without a window, which renders only through
+    /// [`Renderer::capture`], for exporting images.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        line: bool,
+        depth_mode: DepthMode,
+    ) -> Result<Renderer> {
+        let instance = wgpu::Instance::default();
+        let adapter = Self::request_adapter(&instance, None).await?;
+
+        // Describes the offscreen targets, like the window's surface would
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+
+        Self::with_surface(adapter, None, config, line, depth_mode).await
+    }
+
+    async fn request_adapter(
+        instance: &wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface>,
+    ) -> Result<wgpu::Adapter> {
+        // An adapter identifies an implementation of WebGPU on the system.
+        let adapter_options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface,
+        };
+        let adapter = instance
+            .request_adapter(&adapter_options)
+            .await
+            .ok_or_else(|| anyhow!("Failed to find an appropriate GPU adapter"))?;
+
+        info!("Supported features: {:?}", adapter.features());
+
+        Ok(adapter)
+    }
+
+    async fn with_surface(
+        adapter: wgpu::Adapter,
+        surface: Option<wgpu::Surface>,
+        config: wgpu::SurfaceConfiguration,
+        line: bool,
+        depth_mode: DepthMode,
+    ) -> Result<Renderer> {
         // A device is the logical instantiation of an adapter.
         let device_descriptor = wgpu::DeviceDescriptor {
             label: None,
@@ -85,7 +167,9 @@ impl Renderer {
         };
         let (device, queue) = adapter.request_device(&device_descriptor, None).await?;
 
-        surface.configure(&device, &config);
+        if let Some(surface) = &surface {
+            surface.configure(&device, &config);
+        }
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
 
@@ -98,64 +182,67 @@ impl Renderer {
         let material_bind_group_layout =
             device.create_bind_group_layout(&Material::BIND_GROUP_LAYOUT_DESCRIPTOR);
 
+        let scene_color_bind_group_layout =
+            device.create_bind_group_layout(&SceneColor::BIND_GROUP_LAYOUT_DESCRIPTOR);
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: None,
             bind_group_layouts: &[
                 &scene_bind_group_layout,
                 &model_bind_group_layout,
                 &material_bind_group_layout,
+                &scene_color_bind_group_layout,
             ],
             push_constant_ranges: &[],
         });
 
-        let vertex_buffer_layouts = VertexIn::BUFFER_LAYOUTS;
-
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vertex_main",
-                buffers: &vertex_buffer_layouts,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fragment_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    // Use the srgb format for the swapchain.
-                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                polygon_mode: if line {
-                    wgpu::PolygonMode::Line
-                } else {
-                    wgpu::PolygonMode::Fill
-                },
-                ..Default::default()
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
+        let topologies = [
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::PrimitiveTopology::LineList,
+            wgpu::PrimitiveTopology::LineStrip,
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PrimitiveTopology::TriangleStrip,
+        ];
+        // Pipelines for quantized vertex formats are created as models need them
+        let vertex_formats = VertexFormats::default();
+        let pipelines = [ShadingModel::Lit, ShadingModel::Unlit]
+            .into_iter()
+            .flat_map(|shading_model| topologies.map(|topology| (shading_model, topology)))
+            .map(|(shading_model, topology)| {
+                let pipeline = Self::create_pipeline(
+                    &device,
+                    &pipeline_layout,
+                    &shader,
+                    shading_model,
+                    topology,
+                    vertex_formats,
+                    line,
+                    depth_mode,
+                );
+                ((shading_model, topology, vertex_formats), pipeline)
+            })
+            .collect();
 
         let scene = Scene::new(
             config.width,
             config.height,
+            depth_mode,
             &device,
             &scene_bind_group_layout,
         );
 
+        let default_material = Material::new(
+            resources::Material::default(),
+            &[],
+            &device,
+            &queue,
+            &material_bind_group_layout,
+        );
+
         let depth_texture = device.create_depth_texture(&config);
 
+        let scene_color = SceneColor::new(&config, &device, &scene_color_bind_group_layout);
+
         Ok(Renderer {
             adapter,
             surface,
@@ -163,37 +250,211 @@ impl Renderer {
             device,
             queue,
             shader,
-            pipeline,
+            pipeline_layout,
+            pipelines,
+            line,
+            default_material,
+            depth_mode,
             depth_texture,
+            scene_color,
             bind_group_layouts: BindGroupLayouts {
                 scene: scene_bind_group_layout,
                 model: model_bind_group_layout,
                 material: material_bind_group_layout,
+                scene_color: scene_color_bind_group_layout,
             },
             scene,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        shading_model: ShadingModel,
+        topology: wgpu::PrimitiveTopology,
+        vertex_formats: VertexFormats,
+        line: bool,
+        depth_mode: DepthMode,
+    ) -> wgpu::RenderPipeline {
+        let vertex_attributes = vertex_formats.attributes();
+        let vertex_buffer_layouts = VertexIn::buffer_layouts(&vertex_attributes)
+            .into_iter()
+            .chain([InstanceIn::BUFFER_LAYOUT])
+            .collect::<Vec<_>>();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&format!("{:?} {:?} Pipeline", shading_model, topology)),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vertex_main",
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: match shading_model {
+                    ShadingModel::Lit => "fragment_main",
+                    ShadingModel::Unlit => "fragment_unlit",
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    // Use the srgb format for the swapchain.
+                    format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology,
+                // Strips must declare the format of the index buffers drawn with them.
+                strip_index_format: topology.is_strip().then_some(wgpu::IndexFormat::Uint32),
+                polygon_mode: if line {
+                    wgpu::PolygonMode::Line
+                } else {
+                    wgpu::PolygonMode::Fill
+                },
+                ..Default::default()
+            },
+            depth_stencil: Some(depth_mode.depth_stencil_state()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
     pub fn size_changed(&mut self, width: u32, height: u32) {
         self.config.width = width;
         self.config.height = height;
         self.depth_texture = self.device.create_depth_texture(&self.config);
+        self.scene_color = SceneColor::new(
+            &self.config,
+            &self.device,
+            &self.bind_group_layouts.scene_color,
+        );
         self.scene.resize_viewport(width, height, &self.queue);
-        self.surface.configure(&self.device, &self.config);
-    }
-
-    pub fn rotate_camera(&mut self, x: f32, y: f32) {
-        self.scene.rotate_camera(glam::Vec2::new(x, y), &self.queue);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
     }
 
     pub fn zoom_camera(&mut self, delta: f32) {
         self.scene.zoom_camera(delta, &self.queue);
     }
 
+    pub fn move_camera(&mut self, motion: &CameraMotion) {
+        self.scene.move_camera(motion, &self.queue);
+    }
+
+    pub fn set_orbit_mode(&mut self, orbit_mode: OrbitMode) {
+        self.scene.set_orbit_mode(orbit_mode, &self.queue);
+    }
+
+    pub fn toggle_fly_camera(&mut self) {
+        self.scene.toggle_fly_camera(&self.queue);
+    }
+
+    pub fn frame_camera(&mut self) {
+        self.scene.frame_camera(&self.queue);
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        self.scene.set_view(view, &self.queue);
+    }
+
+    pub fn toggle_projection(&mut self) {
+        self.scene.toggle_projection(&self.queue);
+    }
+
+    /// Splits the window into the viewports of `layout`.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.scene.set_layout(layout, &self.queue);
+    }
+
+    /// Sets the distance between the eyes of the stereo layout.
+    pub fn set_eye_separation(&mut self, eye_separation: f32) {
+        self.scene.set_eye_separation(eye_separation, &self.queue);
+    }
+
+    /// Advances animations by `delta_time` seconds. Returns whether another
+    /// frame is needed.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        self.scene.update(delta_time, &self.queue)
+    }
+
+    pub fn variants(&self) -> Vec<&str> {
+        self.scene.variants()
+    }
+
+    pub fn set_variant(&mut self, name: Option<&str>) -> bool {
+        self.scene.set_variant(name)
+    }
+
+    pub fn camera_names(&self) -> Vec<String> {
+        self.scene.camera_names()
+    }
+
+    /// Views the scene through the glTF camera called `name`, or with the
+    /// index `name`, or through the orbit camera if `name` is `None`.
+    ///
+    /// Returns `false`, leaving the camera as it is, if there is no such camera.
+    pub fn set_camera(&mut self, name: Option<&str>) -> bool {
+        let index = match name {
+            Some(name) => match self.scene.find_camera(name) {
+                Some(index) => Some(index),
+                None => return false,
+            },
+            None => None,
+        };
+
+        self.scene.set_camera(index, &self.queue)
+    }
+
+    pub fn bookmarks(&self) -> &[Bookmark] {
+        &self.scene.bookmarks
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<Bookmark>) {
+        self.scene.bookmarks = bookmarks;
+    }
+
+    /// Saves the current view as the bookmark called `name`.
+    pub fn save_bookmark(&mut self, name: String) {
+        self.scene.save_bookmark(name);
+    }
+
+    /// Starts moving the camera to the bookmark called `name`.
+    ///
+    /// Returns `false`, leaving the camera as it is, if there is no such bookmark.
+    pub fn go_to_bookmark(&mut self, name: &str) -> bool {
+        match self.scene.find_bookmark(name).cloned() {
+            Some(bookmark) => {
+                self.scene.go_to_bookmark(&bookmark, &self.queue);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the camera to the bookmark called `name` at once.
+    ///
+    /// Returns `false`, leaving the camera as it is, if there is no such bookmark.
+    pub fn restore_bookmark(&mut self, name: &str) -> bool {
+        match self.scene.find_bookmark(name).cloned() {
+            Some(bookmark) => {
+                self.scene.restore_bookmark(&bookmark, &self.queue);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn render(&self) {
         tracing::debug!("Rendering new frame");
-        let frame = self
-            .surface
+        let surface = match &self.surface {
+            Some(surface) => surface,
+            None => return,
+        };
+        let frame = surface
             .get_current_texture()
             .expect("Failed to acquire next swap chain texture");
         // Create a `-srgb` view of the swapchain texture.
@@ -205,43 +466,189 @@ impl Renderer {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.3,
-                            g: 0.3,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
+
+        self.render_into(&mut encoder, &view);
+
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    /// Renders the scene offscreen at the size of the surface, and reads it
+    /// back.
+    pub async fn capture(&self) -> Result<image::RgbaImage> {
+        let (width, height) = (self.config.width, self.config.height);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Same format as the swapchain view the pipelines render to
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Rows of a texture copy are aligned in the buffer
+        let bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        self.render_into(&mut encoder, &view);
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            let _ = sender.send(result);
+        });
+        // Native platforms only map buffers while polled, the web on its own
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.await??;
+
+        let pixels = {
+            let data = slice.get_mapped_range();
+            data.chunks(bytes_per_row as usize)
+                .flat_map(|row| row[..(width * 4) as usize].chunks(4))
+                // BGRA to RGBA
+                .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+                .collect()
+        };
+        buffer.unmap();
+
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow!("Captured image does not match its size"))
+    }
+
+    /// Records rendering the scene into `view`, a target the size of the
+    /// surface.
+    fn render_into(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        // Transmissive materials see the opaque scene through a separately rendered copy
+        if self.scene.has_transmission() {
+            self.render_phases(
+                encoder,
+                &self.scene_color.view,
+                &self.scene_color.placeholder_bind_group,
+                &[RenderPhase::Opaque],
+            );
+        }
+
+        self.render_phases(
+            encoder,
+            view,
+            &self.scene_color.bind_group,
+            &[RenderPhase::Opaque, RenderPhase::Transmissive],
+        );
+    }
+
+    fn render_phases(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        scene_color_bind_group: &wgpu::BindGroup,
+        phases: &[RenderPhase],
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.3,
+                        g: 0.3,
+                        b: 0.3,
+                        a: 1.0,
                     }),
-                    stencil_ops: None,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.depth_mode.clear_value()),
+                    store: true,
                 }),
-            });
-            render_pass.set_pipeline(&self.pipeline);
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_bind_group(SceneColor::BIND_GROUP_INDEX, scene_color_bind_group, &[]);
 
-            self.scene.render(&mut render_pass);
+        for &phase in phases {
+            self.scene.render(
+                &self.pipelines,
+                &self.default_material,
+                phase,
+                &mut render_pass,
+            );
         }
+    }
 
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
+    /// Creates the pipelines for any vertex formats of `model` not seen before.
+    fn create_model_pipelines(&mut self, model: &Model) {
+        let primitives = model.meshes.iter().flat_map(|mesh| &mesh.primitives);
+
+        for primitive in primitives {
+            for shading_model in [ShadingModel::Lit, ShadingModel::Unlit] {
+                let key = (shading_model, primitive.topology, primitive.vertex_formats);
+
+                if !self.pipelines.contains_key(&key) {
+                    info!(?key, "Creating pipeline");
+
+                    let pipeline = Self::create_pipeline(
+                        &self.device,
+                        &self.pipeline_layout,
+                        &self.shader,
+                        shading_model,
+                        primitive.topology,
+                        primitive.vertex_formats,
+                        self.line,
+                        self.depth_mode,
+                    );
+
+                    self.pipelines.insert(key, pipeline);
+                }
+            }
+        }
     }
 
     pub fn set_model(&mut self, model: Model) {
+        self.create_model_pipelines(&model);
         self.scene.clear_models();
         self.scene.add_model(model);
+        // Frame the orbit camera on the new model's bounds
+        self.scene.frame_camera(&self.queue);
     }
 
     pub fn load_resources(&mut self, resources: Resources) {
@@ -293,7 +700,9 @@ impl Renderer {
             name: Some("Root".to_string()),
             transform: glam::Mat4::from_diagonal(glam::Vec4::new(-1.0, 1.0, 1.0, 1.0)),
             mesh_index: None,
+            camera_index: None,
             children: root_entity_indices,
+            instances: None,
         };
 
         let model = Model::new(
@@ -302,9 +711,10 @@ impl Renderer {
             entities,
             root_entity,
             &self.device,
-            &self.queue,
             &self.bind_group_layouts.model,
-        );
+        )
+        .with_variants(resources.variants)
+        .with_cameras(&resources.cameras);
 
         self.set_model(model);
     }
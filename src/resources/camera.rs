@@ -0,0 +1,67 @@
+use gltf::camera::Projection as GltfProjection;
+
+use crate::depth::DepthMode;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians
+        yfov: f32,
+        znear: f32,
+        /// `None` for an infinite projection
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        /// Half the width of the view volume
+        xmag: f32,
+        /// Half the height of the view volume
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl Projection {
+    /// The projection matrix for a viewport of the given aspect ratio, which
+    /// takes the place of the camera's own one so that the image isn't
+    /// stretched.
+    pub fn matrix(&self, aspect_ratio: f32, depth_mode: DepthMode) -> glam::Mat4 {
+        match *self {
+            Projection::Perspective { yfov, znear, zfar } => {
+                depth_mode.perspective(yfov, aspect_ratio, znear, zfar)
+            }
+            Projection::Orthographic {
+                ymag, znear, zfar, ..
+            } => depth_mode.orthographic(ymag * aspect_ratio, ymag, znear, zfar),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub name: Option<String>,
+    pub projection: Projection,
+}
+
+impl From<gltf::Camera<'_>> for Camera {
+    fn from(camera: gltf::Camera) -> Self {
+        let projection = match camera.projection() {
+            GltfProjection::Perspective(perspective) => Projection::Perspective {
+                yfov: perspective.yfov(),
+                znear: perspective.znear(),
+                zfar: perspective.zfar(),
+            },
+            GltfProjection::Orthographic(orthographic) => Projection::Orthographic {
+                xmag: orthographic.xmag(),
+                ymag: orthographic.ymag(),
+                znear: orthographic.znear(),
+                zfar: orthographic.zfar(),
+            },
+        };
+
+        Self {
+            name: camera.name().map(str::to_owned),
+            projection,
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use anyhow::{anyhow, bail, Context};
+use draco_oxide_core::{attribute::ComponentDataType, types::PointIdx};
+use gltf::json::{
+    self,
+    accessor::{ComponentType, GenericComponentType},
+    validation::{Checked, USize64},
+    Value,
+};
+use tracing::{debug, info};
+
+use super::Buffer;
+use crate::Result;
+
+pub const KHR_DRACO_MESH_COMPRESSION: &str = "KHR_draco_mesh_compression";
+pub const EXT_MESHOPT_COMPRESSION: &str = "EXT_meshopt_compression";
+
+/// Whether `buffer` is an `EXT_meshopt_compression` fallback buffer without
+/// any data of its own, only ever read through compressed buffer views.
+pub fn is_meshopt_fallback(buffer: &gltf::Buffer) -> bool {
+    matches!(buffer.source(), gltf::buffer::Source::Bin)
+        && buffer
+            .extension_value(EXT_MESHOPT_COMPRESSION)
+            .and_then(|extension| extension.get("fallback"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+}
+
+/// Decodes compressed geometry in place, so that accessors read plain data
+/// through regular buffer views afterwards.
+///
+/// Decoded data is appended as new buffers, and the compressed buffer views
+/// and primitives are rewired to point at them.
+pub fn decompress(
+    document: gltf::Document,
+    mut buffers: Vec<Buffer>,
+) -> Result<(gltf::Document, Vec<Buffer>)> {
+    let used = document
+        .extensions_used()
+        .filter(|name| [KHR_DRACO_MESH_COMPRESSION, EXT_MESHOPT_COMPRESSION].contains(name))
+        .collect::<Vec<_>>();
+
+    if used.is_empty() {
+        return Ok((document, buffers));
+    }
+
+    info!(?used, "Decompressing geometry");
+
+    let mut root = document.into_json();
+
+    decompress_meshopt(&mut root, &mut buffers)?;
+    decompress_draco(&mut root, &mut buffers)?;
+
+    Ok((gltf::Document::from_json_without_validation(root), buffers))
+}
+
+fn push_buffer(root: &mut json::Root, buffers: &mut Vec<Buffer>, data: Buffer) -> usize {
+    root.buffers.push(json::Buffer {
+        byte_length: USize64::from(data.len()),
+        name: None,
+        uri: None,
+        extensions: None,
+        extras: Default::default(),
+    });
+    buffers.push(data);
+    buffers.len() - 1
+}
+
+fn extension_usize(extension: &Value, key: &str) -> Result<usize> {
+    extension
+        .get(key)
+        .and_then(Value::as_u64)
+        .map(|value| value as usize)
+        .ok_or_else(|| anyhow!("Missing or invalid `{key}`"))
+}
+
+fn decompress_meshopt(root: &mut json::Root, buffers: &mut Vec<Buffer>) -> Result<()> {
+    for view_index in 0..root.buffer_views.len() {
+        let Some(extension) = root.buffer_views[view_index]
+            .extensions
+            .as_mut()
+            .and_then(|extensions| extensions.others.remove(EXT_MESHOPT_COMPRESSION))
+        else {
+            continue;
+        };
+
+        let data = decode_meshopt_view(&extension, buffers)
+            .with_context(|| format!("Failed to decode meshopt buffer view {view_index}"))?;
+
+        debug!(view_index, len = data.len(), "Decoded meshopt buffer view");
+
+        let byte_length = USize64::from(data.len());
+        let buffer_index = push_buffer(root, buffers, data);
+
+        let view = &mut root.buffer_views[view_index];
+        view.buffer = json::Index::new(buffer_index as u32);
+        view.byte_offset = None;
+        view.byte_length = byte_length;
+    }
+
+    Ok(())
+}
+
+fn decode_meshopt_view(extension: &Value, buffers: &[Buffer]) -> Result<Buffer> {
+    let buffer = extension_usize(extension, "buffer")?;
+    let offset = extension
+        .get("byteOffset")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as usize;
+    let length = extension_usize(extension, "byteLength")?;
+    let stride = extension_usize(extension, "byteStride")?;
+    let count = extension_usize(extension, "count")?;
+    let mode = extension
+        .get("mode")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let filter = extension
+        .get("filter")
+        .and_then(Value::as_str)
+        .unwrap_or("NONE");
+
+    let source = buffers
+        .get(buffer)
+        .and_then(|buffer| buffer.get(offset..offset + length))
+        .ok_or_else(|| anyhow!("Compressed data is out of bounds of buffer {buffer}"))?;
+
+    let data = match mode {
+        "ATTRIBUTES" => {
+            let mut data = decode_meshopt_vertices(source, count, stride)?;
+            apply_meshopt_filter(&mut data, filter, stride)?;
+            data
+        }
+        "TRIANGLES" | "INDICES" => {
+            let mut indices = vec![0u32; count];
+            if mode == "TRIANGLES" {
+                meshopt_rs::index::buffer::decode_index_buffer(&mut indices, source)
+            } else {
+                meshopt_rs::index::sequence::decode_index_sequence(&mut indices, source)
+            }
+            .map_err(|err| anyhow!("Invalid index data: {err:?}"))?;
+
+            match stride {
+                2 => indices
+                    .iter()
+                    .flat_map(|&index| (index as u16).to_le_bytes())
+                    .collect(),
+                4 => bytemuck::cast_slice(&indices).to_vec(),
+                _ => bail!("Invalid index stride {stride}"),
+            }
+        }
+        _ => bail!("Unknown compression mode {mode:?}"),
+    };
+
+    Ok(data)
+}
+
+/// The vertex codec works on whole vertices, so dispatch to a fixed-size
+/// vertex for every stride the extension allows.
+fn decode_meshopt_vertices(source: &[u8], count: usize, stride: usize) -> Result<Buffer> {
+    macro_rules! decode {
+        ($($stride:literal)*) => {
+            match stride {
+                $($stride => {
+                    let mut vertices = vec![[0u8; $stride]; count];
+                    meshopt_rs::vertex::buffer::decode_vertex_buffer(&mut vertices, source)
+                        .map_err(|err| anyhow!("Invalid vertex data: {err:?}"))?;
+                    vertices.concat()
+                })*
+                _ => bail!("Invalid vertex stride {stride}"),
+            }
+        };
+    }
+
+    Ok(decode!(
+        4 8 12 16 20 24 28 32 36 40 44 48 52 56 60 64
+        68 72 76 80 84 88 92 96 100 104 108 112 116 120 124 128
+        132 136 140 144 148 152 156 160 164 168 172 176 180 184 188 192
+        196 200 204 208 212 216 220 224 228 232 236 240 244 248 252 256
+    ))
+}
+
+fn apply_meshopt_filter(data: &mut [u8], filter: &str, stride: usize) -> Result<()> {
+    use meshopt_rs::vertex::filter;
+
+    // The filters work on typed elements, so go through aligned copies
+    fn filtered<T: bytemuck::Pod + Default>(data: &mut [u8], decode: impl FnOnce(&mut [T])) {
+        let mut elements = vec![T::default(); data.len() / std::mem::size_of::<T>()];
+        bytemuck::cast_slice_mut::<_, u8>(&mut elements).copy_from_slice(data);
+        decode(&mut elements);
+        data.copy_from_slice(bytemuck::cast_slice(&elements));
+    }
+
+    match (filter, stride) {
+        ("NONE", _) => {}
+        ("OCTAHEDRAL", 4) => filtered(data, filter::decode_filter_oct_8),
+        ("OCTAHEDRAL", 8) => filtered(data, filter::decode_filter_oct_16),
+        ("QUATERNION", 8) => filtered(data, filter::decode_filter_quat),
+        ("EXPONENTIAL", _) => filtered(data, filter::decode_filter_exp),
+        _ => bail!("Unsupported filter {filter:?} with stride {stride}"),
+    }
+
+    Ok(())
+}
+
+fn decompress_draco(root: &mut json::Root, buffers: &mut Vec<Buffer>) -> Result<()> {
+    for mesh_index in 0..root.meshes.len() {
+        for primitive_index in 0..root.meshes[mesh_index].primitives.len() {
+            let Some(extension) = root.meshes[mesh_index].primitives[primitive_index]
+                .extensions
+                .as_mut()
+                .and_then(|extensions| extensions.others.remove(KHR_DRACO_MESH_COMPRESSION))
+            else {
+                continue;
+            };
+
+            decode_draco_primitive(root, buffers, mesh_index, primitive_index, &extension)
+                .with_context(|| {
+                    format!(
+                        "Failed to decode Draco primitive {primitive_index} of mesh {mesh_index}"
+                    )
+                })?;
+
+            debug!(mesh_index, primitive_index, "Decoded Draco primitive");
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_draco_primitive(
+    root: &mut json::Root,
+    buffers: &mut Vec<Buffer>,
+    mesh_index: usize,
+    primitive_index: usize,
+    extension: &Value,
+) -> Result<()> {
+    let view_index = extension_usize(extension, "bufferView")?;
+    let view = root
+        .buffer_views
+        .get(view_index)
+        .ok_or_else(|| anyhow!("Invalid buffer view {view_index}"))?;
+    let offset = view
+        .byte_offset
+        .map(|offset| offset.0 as usize)
+        .unwrap_or(0);
+    let length = view.byte_length.0 as usize;
+    let source = buffers
+        .get(view.buffer.value())
+        .and_then(|buffer| buffer.get(offset..offset + length))
+        .ok_or_else(|| anyhow!("Compressed data is out of bounds of its buffer"))?;
+
+    let mesh = draco_oxide_decoder::decode_mesh(source)
+        .map_err(|err| anyhow!("Invalid Draco data: {err}"))?;
+
+    let attribute_ids = extension
+        .get("attributes")
+        .and_then(Value::as_object)
+        .ok_or_else(|| anyhow!("Missing or invalid `attributes`"))?;
+
+    let primitive = &root.meshes[mesh_index].primitives[primitive_index];
+
+    // Accessor index and decoded data for every attribute, plus the indices
+    let mut decoded = vec![];
+
+    for (semantic, accessor) in &primitive.attributes {
+        let Some(id) = attribute_ids
+            .get(&semantic.to_string())
+            .and_then(Value::as_u64)
+        else {
+            continue;
+        };
+
+        let attribute = mesh
+            .attributes
+            .iter()
+            .find(|attribute| attribute.get_id().as_usize() as u64 == id)
+            .ok_or_else(|| anyhow!("No Draco attribute with id {id}"))?;
+
+        let (component_type, normalized, components) = accessor_format(root, accessor.value())?;
+        if attribute.get_num_components() < components {
+            bail!(
+                "Draco attribute {id} has {} components, expected {components}",
+                attribute.get_num_components()
+            );
+        }
+
+        let draco_type = attribute.get_component_type();
+        let values = attribute.get_data_as_bytes();
+        let value_size = draco_type.size() * attribute.get_num_components();
+
+        let mut data = Vec::with_capacity(attribute.len() * components * component_type.size());
+        for point in 0..attribute.len() {
+            let value = usize::from(attribute.get_unique_val_idx(PointIdx::from(point)));
+            let value = &values[value * value_size..];
+            for component in 0..components {
+                let value = read_component(value, draco_type, component)?;
+                write_component(&mut data, value, component_type, normalized, draco_type);
+            }
+        }
+
+        decoded.push((accessor.value(), attribute.len(), data));
+    }
+
+    if let Some(accessor) = primitive.indices {
+        let (component_type, _, _) = accessor_format(root, accessor.value())?;
+
+        let mut data = Vec::with_capacity(mesh.faces.len() * 3 * component_type.size());
+        for &index in mesh.faces.iter().flatten() {
+            let index = usize::from(index) as f64;
+            write_component(
+                &mut data,
+                index,
+                component_type,
+                false,
+                ComponentDataType::U32,
+            );
+        }
+
+        decoded.push((accessor.value(), mesh.faces.len() * 3, data));
+    }
+
+    // One buffer per primitive, with a tightly packed view per accessor
+    let mut data = vec![];
+    let buffer_index = root.buffers.len();
+
+    for (accessor_index, count, accessor_data) in decoded {
+        let view_index = root.buffer_views.len();
+        root.buffer_views.push(json::buffer::View {
+            buffer: json::Index::new(buffer_index as u32),
+            byte_length: USize64::from(accessor_data.len()),
+            byte_offset: Some(USize64::from(data.len())),
+            byte_stride: None,
+            name: None,
+            target: None,
+            extensions: None,
+            extras: Default::default(),
+        });
+
+        data.extend(accessor_data);
+        data.resize(data.len().next_multiple_of(4), 0);
+
+        let accessor = &mut root.accessors[accessor_index];
+        accessor.buffer_view = Some(json::Index::new(view_index as u32));
+        accessor.byte_offset = None;
+        accessor.count = USize64::from(count);
+    }
+
+    push_buffer(root, buffers, data);
+
+    Ok(())
+}
+
+/// Component type, normalization and component count of an accessor
+fn accessor_format(root: &json::Root, index: usize) -> Result<(ComponentType, bool, usize)> {
+    let accessor = root
+        .accessors
+        .get(index)
+        .ok_or_else(|| anyhow!("Invalid accessor {index}"))?;
+
+    match (accessor.component_type, accessor.type_) {
+        (Checked::Valid(GenericComponentType(component_type)), Checked::Valid(type_)) => {
+            Ok((component_type, accessor.normalized, type_.multiplicity()))
+        }
+        _ => bail!("Invalid format of accessor {index}"),
+    }
+}
+
+fn read_component(value: &[u8], ty: ComponentDataType, component: usize) -> Result<f64> {
+    let size = ty.size();
+    let bytes = value
+        .get(component * size..(component + 1) * size)
+        .ok_or_else(|| anyhow!("Draco attribute data is truncated"))?;
+
+    Ok(match ty {
+        ComponentDataType::I8 => bytes[0] as i8 as f64,
+        ComponentDataType::U8 => bytes[0] as f64,
+        ComponentDataType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        ComponentDataType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        ComponentDataType::I32 => i32::from_le_bytes(bytes.try_into()?) as f64,
+        ComponentDataType::U32 => u32::from_le_bytes(bytes.try_into()?) as f64,
+        ComponentDataType::F32 => f32::from_le_bytes(bytes.try_into()?) as f64,
+        ComponentDataType::F64 => f64::from_le_bytes(bytes.try_into()?),
+        _ => bail!("Unsupported Draco component type {ty:?}"),
+    })
+}
+
+/// Writes a decoded value in the accessor's format. Float values headed for
+/// a normalized integer accessor are scaled to the integer range.
+fn write_component(
+    data: &mut Vec<u8>,
+    value: f64,
+    component_type: ComponentType,
+    normalized: bool,
+    draco_type: ComponentDataType,
+) {
+    let scale = |max: f64| {
+        if normalized && draco_type.is_float() {
+            (value * max).round()
+        } else {
+            value
+        }
+    };
+
+    match component_type {
+        ComponentType::I8 => data.push(scale(i8::MAX as f64) as i8 as u8),
+        ComponentType::U8 => data.push(scale(u8::MAX as f64) as u8),
+        ComponentType::I16 => data.extend((scale(i16::MAX as f64) as i16).to_le_bytes()),
+        ComponentType::U16 => data.extend((scale(u16::MAX as f64) as u16).to_le_bytes()),
+        ComponentType::U32 => data.extend((value as u32).to_le_bytes()),
+        ComponentType::F32 => data.extend((value as f32).to_le_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit quad in the XY plane, encoded with draco_encoder
+    const DRACO_QUAD: &[u8] = &[
+        0x44, 0x52, 0x41, 0x43, 0x4f, 0x02, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x04, 0x02, 0x02,
+        0x02, 0x00, 0x00, 0x01, 0x1f, 0xff, 0x01, 0x11, 0xff, 0x01, 0x11, 0xff, 0x01, 0x11, 0x03,
+        0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x09, 0x03, 0x00, 0x00,
+        0x02, 0x01, 0x03, 0x09, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01, 0x09, 0x03, 0x00, 0x02, 0x03,
+        0x01, 0x01, 0x01, 0x00, 0x03, 0x03, 0x01, 0x30, 0x01, 0x10, 0x03, 0x00, 0x28, 0x82, 0x98,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x80, 0xbf, 0x00, 0x00,
+        0x80, 0xbf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x0e, 0x05, 0x01, 0x01, 0x00,
+        0x02, 0x03, 0x01, 0x40, 0x01, 0x00, 0x0c, 0x02, 0x00, 0x00, 0x00, 0x80, 0x02, 0x80, 0x70,
+        0x00, 0x00, 0x00, 0x00, 0xff, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x80, 0x3f, 0x0a, 0x06, 0x03, 0x01, 0x01, 0x01, 0x01, 0x01, 0x40, 0x01,
+        0x00, 0xff, 0x00, 0x00, 0x00, 0x7f, 0x00, 0x00, 0x00, 0xff, 0x02, 0x44, 0x40, 0x08,
+    ];
+    /// The same quad at half size, encoded with meshoptimizer
+    const MESHOPT_VERTICES: &[u8] = &[
+        0xa0, 0x00, 0x00, 0x00, 0x01, 0x33, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+        0x0c, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0xbf, 0x00, 0x00, 0x00, 0xbf, 0x00, 0x00, 0x00, 0x00,
+    ];
+    const MESHOPT_INDICES: &[u8] = &[
+        0xe0, 0xf0, 0x00, 0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98,
+        0x01, 0x69, 0x00, 0x00,
+    ];
+
+    fn document(json: Value) -> gltf::Document {
+        let root = json::deserialize::from_value(json).unwrap();
+        gltf::Document::from_json_without_validation(root)
+    }
+
+    fn read(document: &gltf::Document, buffers: &[Buffer]) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let primitive = document
+            .meshes()
+            .next()
+            .unwrap()
+            .primitives()
+            .next()
+            .unwrap();
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions = reader.read_positions().unwrap().collect();
+        let indices = reader.read_indices().unwrap().into_u32().collect();
+        (positions, indices)
+    }
+
+    #[test]
+    fn decodes_draco_primitives() {
+        let document = document(serde_json::json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": [KHR_DRACO_MESH_COMPRESSION],
+            "meshes": [{ "primitives": [{
+                "attributes": { "POSITION": 0 },
+                "indices": 1,
+                "extensions": { KHR_DRACO_MESH_COMPRESSION: {
+                    "bufferView": 0,
+                    "attributes": { "POSITION": 0 },
+                } },
+            }] }],
+            "accessors": [
+                { "componentType": 5126, "count": 4, "type": "VEC3" },
+                { "componentType": 5123, "count": 6, "type": "SCALAR" },
+            ],
+            "bufferViews": [{ "buffer": 0, "byteLength": DRACO_QUAD.len() }],
+            "buffers": [{ "byteLength": DRACO_QUAD.len() }],
+        }));
+
+        let (document, buffers) = decompress(document, vec![DRACO_QUAD.to_vec()]).unwrap();
+        let (positions, indices) = read(&document, &buffers);
+
+        // Draco may reorder points, so compare the triangles' corners
+        let corners = |triangle: &[u32]| {
+            let mut corners = triangle
+                .iter()
+                .map(|&index| positions[index as usize])
+                .collect::<Vec<_>>();
+            corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            corners
+        };
+        assert_eq!(indices.len(), 6);
+        let mut triangles = indices.chunks(3).map(corners).collect::<Vec<_>>();
+        triangles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            triangles,
+            [
+                [[-1.0, -1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+                [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0]],
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_meshopt_buffer_views() {
+        let mut data = MESHOPT_VERTICES.to_vec();
+        let indices_offset = data.len();
+        data.extend(MESHOPT_INDICES);
+
+        let document = document(serde_json::json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": [EXT_MESHOPT_COMPRESSION],
+            "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR" },
+            ],
+            "bufferViews": [
+                {
+                    "buffer": 1, "byteLength": 48, "byteStride": 12,
+                    "extensions": { EXT_MESHOPT_COMPRESSION: {
+                        "buffer": 0, "byteLength": MESHOPT_VERTICES.len(),
+                        "byteStride": 12, "count": 4, "mode": "ATTRIBUTES",
+                    } },
+                },
+                {
+                    "buffer": 1, "byteOffset": 48, "byteLength": 12,
+                    "extensions": { EXT_MESHOPT_COMPRESSION: {
+                        "buffer": 0, "byteOffset": indices_offset,
+                        "byteLength": MESHOPT_INDICES.len(),
+                        "byteStride": 2, "count": 6, "mode": "TRIANGLES",
+                    } },
+                },
+            ],
+            "buffers": [
+                { "byteLength": data.len() },
+                { "byteLength": 60, "extensions": { EXT_MESHOPT_COMPRESSION: { "fallback": true } } },
+            ],
+        }));
+
+        // The fallback buffer has no data of its own
+        let (document, buffers) = decompress(document, vec![data, vec![]]).unwrap();
+        let (positions, indices) = read(&document, &buffers);
+
+        assert_eq!(
+            positions,
+            [
+                [-0.5, -0.5, 0.0],
+                [0.5, -0.5, 0.0],
+                [0.5, 0.5, 0.0],
+                [-0.5, 0.5, 0.0],
+            ]
+        );
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+    }
+}
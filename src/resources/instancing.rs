@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Context, Result};
+use gltf::accessor::{Item, Iter};
+
+use super::QuantizedAttribute;
+
+pub const EXT_MESH_GPU_INSTANCING: &str = "EXT_mesh_gpu_instancing";
+
+/// Reads the local transforms of the `EXT_mesh_gpu_instancing` instances of
+/// `node`, or returns `None` if it is not instanced.
+pub fn read_instances<'a, 's, F>(
+    node: &gltf::Node<'a>,
+    document: &'a gltf::Document,
+    get_buffer_data: F,
+) -> Result<Option<Vec<glam::Mat4>>>
+where
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    let extension = match node
+        .extensions()
+        .and_then(|extensions| extensions.get(EXT_MESH_GPU_INSTANCING))
+    {
+        Some(extension) => extension,
+        None => return Ok(None),
+    };
+
+    let attribute = |semantic: &str| -> Result<Option<gltf::Accessor<'a>>> {
+        let index = match extension
+            .get("attributes")
+            .and_then(|attributes| attributes.get(semantic))
+        {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        index
+            .as_u64()
+            .and_then(|index| document.accessors().nth(index as usize))
+            .map(Some)
+            .ok_or_else(|| anyhow!("Invalid {semantic} accessor: {index}"))
+    };
+
+    let translations = attribute("TRANSLATION")?
+        .map(|accessor| read(accessor, get_buffer_data.clone()))
+        .transpose()
+        .context("Failed to read instance translations")?;
+    let rotations = attribute("ROTATION")?
+        .map(|accessor| read(accessor, get_buffer_data.clone()))
+        .transpose()
+        .context("Failed to read instance rotations")?;
+    let scales = attribute("SCALE")?
+        .map(|accessor| read(accessor, get_buffer_data.clone()))
+        .transpose()
+        .context("Failed to read instance scales")?;
+
+    let count = [
+        translations.as_ref().map(Vec::len),
+        rotations.as_ref().map(Vec::len),
+        scales.as_ref().map(Vec::len),
+    ]
+    .into_iter()
+    .flatten()
+    .min()
+    .unwrap_or(0);
+
+    let instances = (0..count)
+        .map(|index| {
+            let translation = translations
+                .as_ref()
+                .map_or(glam::Vec3::ZERO, |translations| translations[index].into());
+            let rotation = rotations
+                .as_ref()
+                .map_or(glam::Quat::IDENTITY, |rotations| {
+                    glam::Quat::from_array(rotations[index])
+                });
+            let scale = scales
+                .as_ref()
+                .map_or(glam::Vec3::ONE, |scales| scales[index].into());
+
+            glam::Mat4::from_scale_rotation_translation(scale, rotation, translation)
+        })
+        .collect();
+
+    Ok(Some(instances))
+}
+
+/// Reads a float accessor, or an integer one as allowed by
+/// `KHR_mesh_quantization`.
+fn read<'a, 's, F, const N: usize>(
+    accessor: gltf::Accessor<'a>,
+    get_buffer_data: F,
+) -> Result<Vec<[f32; N]>>
+where
+    [f32; N]: Item,
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    if let Some(quantized) = QuantizedAttribute::read(accessor.clone(), get_buffer_data.clone()) {
+        return Ok(quantized.dequantize());
+    }
+
+    let values = Iter::<[f32; N]>::new(accessor, get_buffer_data)
+        .ok_or_else(|| anyhow!("Unexpected accessor data"))?;
+
+    Ok(values.collect())
+}
@@ -1,9 +1,330 @@
+use super::Metadata;
+
+/// `KHR_texture_transform` applied to the texture coordinates of a texture.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureTransform {
+    pub offset: [f32; 2],
+    /// Counter-clockwise rotation of the UVs in radians
+    pub rotation: f32,
+    pub scale: [f32; 2],
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            rotation: 0.0,
+            scale: [1.0, 1.0],
+        }
+    }
+}
+
+impl TextureTransform {
+    /// The UV transform matrix, `translation * rotation * scale` as per the spec.
+    pub fn matrix(&self) -> glam::Mat3 {
+        glam::Mat3::from_scale_angle_translation(
+            self.scale.into(),
+            -self.rotation,
+            self.offset.into(),
+        )
+    }
+}
+
+/// Reference from a material to one of its textures.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureInfo {
+    pub index: usize,
+    /// The `TEXCOORD_n` set the texture is sampled with, including any
+    /// `KHR_texture_transform` override
+    pub tex_coord: u32,
+    pub transform: TextureTransform,
+}
+
+impl From<gltf::texture::Info<'_>> for TextureInfo {
+    fn from(info: gltf::texture::Info) -> Self {
+        let texture_transform = info.texture_transform();
+
+        let transform = texture_transform
+            .as_ref()
+            .map(|transform| TextureTransform {
+                offset: transform.offset(),
+                rotation: transform.rotation(),
+                scale: transform.scale(),
+            })
+            .unwrap_or_default();
+
+        Self {
+            index: info.texture().index(),
+            tex_coord: texture_transform
+                .and_then(|transform| transform.tex_coord())
+                .unwrap_or_else(|| info.tex_coord()),
+            transform,
+        }
+    }
+}
+
+impl From<gltf::material::NormalTexture<'_>> for TextureInfo {
+    fn from(info: gltf::material::NormalTexture) -> Self {
+        // The gltf crate only parses `KHR_texture_transform` on regular texture infos
+        let texture_transform = info
+            .extension_value("KHR_texture_transform")
+            .cloned()
+            .and_then(|value| {
+                gltf::json::deserialize::from_value::<
+                    gltf::json::extensions::texture::TextureTransform,
+                >(value)
+                .ok()
+            });
+
+        Self::from_json(info.texture().index(), info.tex_coord(), texture_transform)
+    }
+}
+
+/// Texture infos of extensions the gltf crate doesn't parse. Normal texture
+/// infos deserialize as well, their `scale` is ignored.
+impl From<gltf::json::texture::Info> for TextureInfo {
+    fn from(info: gltf::json::texture::Info) -> Self {
+        Self::from_json(
+            info.index.value(),
+            info.tex_coord,
+            info.extensions
+                .and_then(|extensions| extensions.texture_transform),
+        )
+    }
+}
+
+impl TextureInfo {
+    fn from_json(
+        index: usize,
+        tex_coord: u32,
+        texture_transform: Option<gltf::json::extensions::texture::TextureTransform>,
+    ) -> Self {
+        let transform = texture_transform
+            .as_ref()
+            .map(|transform| TextureTransform {
+                offset: transform.offset.0,
+                rotation: transform.rotation.0,
+                scale: transform.scale.0,
+            })
+            .unwrap_or_default();
+
+        Self {
+            index,
+            tex_coord: texture_transform
+                .and_then(|transform| transform.tex_coord)
+                .unwrap_or(tex_coord),
+            transform,
+        }
+    }
+}
+
+/// `KHR_materials_clearcoat`: a clear, reflective layer on top of the material.
+#[derive(Clone, Copy, Debug)]
+pub struct Clearcoat {
+    pub factor: f32,
+    pub texture: Option<TextureInfo>,
+    pub roughness_factor: f32,
+    pub roughness_texture: Option<TextureInfo>,
+    pub normal_texture: Option<TextureInfo>,
+}
+
+impl Clearcoat {
+    /// Parses the extension object, which the gltf crate doesn't support.
+    pub fn from_extension(value: &gltf::json::Value) -> Self {
+        Self {
+            factor: json_factor(value, "clearcoatFactor").unwrap_or(0.0),
+            texture: json_texture(value, "clearcoatTexture"),
+            roughness_factor: json_factor(value, "clearcoatRoughnessFactor").unwrap_or(0.0),
+            roughness_texture: json_texture(value, "clearcoatRoughnessTexture"),
+            normal_texture: json_texture(value, "clearcoatNormalTexture"),
+        }
+    }
+}
+
+/// `KHR_materials_sheen`: back-scattering of cloth-like fabrics.
+#[derive(Clone, Copy, Debug)]
+pub struct Sheen {
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<TextureInfo>,
+    pub roughness_factor: f32,
+    pub roughness_texture: Option<TextureInfo>,
+}
+
+impl Sheen {
+    /// Parses the extension object, which the gltf crate doesn't support.
+    pub fn from_extension(value: &gltf::json::Value) -> Self {
+        Self {
+            color_factor: value
+                .get("sheenColorFactor")
+                .cloned()
+                .and_then(|value| gltf::json::deserialize::from_value(value).ok())
+                .unwrap_or([0.0, 0.0, 0.0]),
+            color_texture: json_texture(value, "sheenColorTexture"),
+            roughness_factor: json_factor(value, "sheenRoughnessFactor").unwrap_or(0.0),
+            roughness_texture: json_texture(value, "sheenRoughnessTexture"),
+        }
+    }
+}
+
+/// `KHR_materials_transmission`: light passing through a thin surface.
+#[derive(Clone, Copy, Debug)]
+pub struct Transmission {
+    pub factor: f32,
+    pub texture: Option<TextureInfo>,
+}
+
+impl From<gltf::material::Transmission<'_>> for Transmission {
+    fn from(transmission: gltf::material::Transmission) -> Self {
+        Self {
+            factor: transmission.transmission_factor(),
+            texture: transmission.transmission_texture().map(TextureInfo::from),
+        }
+    }
+}
+
+/// `KHR_materials_volume`: refraction and absorption by a transmissive volume.
+#[derive(Clone, Copy, Debug)]
+pub struct Volume {
+    pub thickness_factor: f32,
+    pub thickness_texture: Option<TextureInfo>,
+    /// Infinite unless the medium absorbs light
+    pub attenuation_distance: f32,
+    pub attenuation_color: [f32; 3],
+}
+
+impl From<gltf::material::Volume<'_>> for Volume {
+    fn from(volume: gltf::material::Volume) -> Self {
+        Self {
+            thickness_factor: volume.thickness_factor(),
+            thickness_texture: volume.thickness_texture().map(TextureInfo::from),
+            attenuation_distance: volume.attenuation_distance(),
+            attenuation_color: volume.attenuation_color(),
+        }
+    }
+}
+
+/// `KHR_materials_specular`: strength and color of dielectric reflections.
+#[derive(Clone, Copy, Debug)]
+pub struct Specular {
+    pub factor: f32,
+    pub texture: Option<TextureInfo>,
+    pub color_factor: [f32; 3],
+    pub color_texture: Option<TextureInfo>,
+}
+
+impl From<gltf::material::Specular<'_>> for Specular {
+    fn from(specular: gltf::material::Specular) -> Self {
+        Self {
+            factor: specular.specular_factor(),
+            texture: specular.specular_texture().map(TextureInfo::from),
+            color_factor: specular.specular_color_factor(),
+            color_texture: specular.specular_color_texture().map(TextureInfo::from),
+        }
+    }
+}
+
+fn json_factor(value: &gltf::json::Value, key: &str) -> Option<f32> {
+    value.get(key)?.as_f64().map(|factor| factor as f32)
+}
+
+fn json_texture(value: &gltf::json::Value, key: &str) -> Option<TextureInfo> {
+    let info = value.get(key)?.clone();
+    gltf::json::deserialize::from_value::<gltf::json::texture::Info>(info)
+        .ok()
+        .map(TextureInfo::from)
+}
+
+/// How a material responds to lighting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ShadingModel {
+    /// Physically based metallic-roughness shading, with the supported extensions
+    #[default]
+    Lit,
+    /// `KHR_materials_unlit`: base color times vertex color, without lighting
+    Unlit,
+}
+
 pub struct Material {
     pub name: Option<String>,
+    pub shading_model: ShadingModel,
     pub base_color_factor: [f32; 4],
-    pub base_color_texture_index: Option<usize>,
-    pub normal_texture_index: Option<usize>,
+    pub base_color_texture: Option<TextureInfo>,
+    pub normal_texture: Option<TextureInfo>,
     pub metallic_factor: f32,
     pub roughness_factor: f32,
-    pub metallic_roughness_texture_index: Option<usize>,
+    pub metallic_roughness_texture: Option<TextureInfo>,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<TextureInfo>,
+    /// `KHR_materials_emissive_strength`
+    pub emissive_strength: f32,
+    pub clearcoat: Option<Clearcoat>,
+    pub sheen: Option<Sheen>,
+    pub transmission: Option<Transmission>,
+    pub volume: Option<Volume>,
+    /// `KHR_materials_ior`
+    pub ior: f32,
+    pub specular: Option<Specular>,
+    pub metadata: Metadata,
+}
+
+impl Default for Material {
+    /// The glTF default material, used by primitives without one.
+    fn default() -> Self {
+        Self {
+            name: None,
+            shading_model: ShadingModel::Lit,
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            normal_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            emissive_factor: [0.0, 0.0, 0.0],
+            emissive_texture: None,
+            emissive_strength: 1.0,
+            clearcoat: None,
+            sheen: None,
+            transmission: None,
+            volume: None,
+            ior: 1.5,
+            specular: None,
+            metadata: Metadata::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    #[test]
+    fn texture_transform_matches_spec() {
+        let (offset, rotation, scale) = ([0.5, 0.25], std::f32::consts::FRAC_PI_2, [2.0, 3.0]);
+        let transform = TextureTransform {
+            offset,
+            rotation,
+            scale,
+        };
+
+        // The matrices from the KHR_texture_transform spec, written column by column
+        let translation = glam::Mat3::from_cols(
+            glam::Vec3::X,
+            glam::Vec3::Y,
+            glam::Vec3::new(offset[0], offset[1], 1.0),
+        );
+        let rotation = glam::Mat3::from_cols(
+            glam::Vec3::new(rotation.cos(), -rotation.sin(), 0.0),
+            glam::Vec3::new(rotation.sin(), rotation.cos(), 0.0),
+            glam::Vec3::Z,
+        );
+        let scale = glam::Mat3::from_diagonal(glam::Vec3::new(scale[0], scale[1], 1.0));
+
+        let matrix = transform.matrix();
+        assert!(matrix.abs_diff_eq(translation * rotation * scale, EPSILON));
+
+        let uv = matrix.transform_point2(glam::Vec2::X);
+        assert!(uv.abs_diff_eq(glam::Vec2::new(0.5, -1.75), EPSILON), "{uv}");
+    }
 }
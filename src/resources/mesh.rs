@@ -1,13 +1,98 @@
+use std::collections::HashMap;
+
+use gltf::accessor::DataType;
+use gltf::mesh::Mode;
+
+use crate::bounds::Aabb;
+
+use super::{Metadata, QuantizedAttributes};
+
 pub struct Primitive {
     pub positions: Vec<[f32; 3]>,
     pub tex_coords: Vec<[f32; 2]>,
     pub normals: Vec<[f32; 3]>,
     pub tangents: Vec<[f32; 4]>,
-    pub indices: Vec<u32>,
-    pub material_index: usize,
+    pub colors: Vec<[f32; 4]>,
+    pub tex_coords_1: Vec<[f32; 2]>,
+    pub indices: Option<Vec<u32>>,
+    pub mode: Mode,
+    /// `None` for primitives using the default material
+    pub material_index: Option<usize>,
+    /// `KHR_materials_variants` mappings from variant index to material index
+    pub material_variants: HashMap<usize, usize>,
+    /// `KHR_mesh_quantization` attributes to upload as they are, in place of
+    /// their `f32` counterparts above
+    pub quantized: QuantizedAttributes,
+    /// Bounds of the positions
+    pub bounds: Aabb,
+}
+
+impl Primitive {
+    /// Returns the indices, or `0..n` for a non-indexed primitive.
+    pub fn vertex_indices(&self) -> Vec<u32> {
+        match &self.indices {
+            Some(indices) => indices.clone(),
+            None => (0..self.positions.len() as u32).collect(),
+        }
+    }
 }
 
 pub struct Mesh {
     pub name: Option<String>,
     pub primitives: Vec<Primitive>,
+    pub metadata: Metadata,
+    /// Bounds of all primitives
+    pub bounds: Aabb,
+}
+
+/// Reads the bounds of a `POSITION` accessor from its `min` and `max`.
+pub fn accessor_bounds(accessor: &gltf::Accessor) -> Option<Aabb> {
+    // Normalized integers as allowed by `KHR_mesh_quantization`
+    let max_value = match accessor.data_type() {
+        _ if !accessor.normalized() => 1.0,
+        DataType::I8 => i8::MAX as f32,
+        DataType::U8 => u8::MAX as f32,
+        DataType::I16 => i16::MAX as f32,
+        DataType::U16 => u16::MAX as f32,
+        DataType::U32 | DataType::F32 => 1.0,
+    };
+
+    let read = |value: gltf::json::Value| -> Option<glam::Vec3> {
+        let values = value.as_array()?;
+        let component = |index: usize| -> Option<f32> {
+            let value = values.get(index)?.as_f64()? as f32 / max_value;
+            Some(if accessor.normalized() {
+                value.max(-1.0)
+            } else {
+                value
+            })
+        };
+        Some(glam::Vec3::new(component(0)?, component(1)?, component(2)?))
+    };
+
+    Some(Aabb {
+        min: read(accessor.min()?)?,
+        max: read(accessor.max()?)?,
+    })
+}
+
+#[cfg(test)]
+impl Primitive {
+    /// A bare triangle list primitive for tests.
+    pub fn from_positions(positions: Vec<[f32; 3]>, indices: Option<Vec<u32>>) -> Self {
+        Self {
+            positions,
+            tex_coords: vec![],
+            normals: vec![],
+            tangents: vec![],
+            colors: vec![],
+            tex_coords_1: vec![],
+            indices,
+            mode: Mode::Triangles,
+            material_index: None,
+            material_variants: HashMap::new(),
+            quantized: QuantizedAttributes::default(),
+            bounds: Aabb::EMPTY,
+        }
+    }
 }
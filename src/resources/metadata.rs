@@ -0,0 +1,56 @@
+use gltf::json::Extras;
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+pub const KHR_XMP_JSON_LD: &str = "KHR_xmp_json_ld";
+
+/// Application specific data of a glTF object: its `extras` and its
+/// `KHR_xmp_json_ld` packet.
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    /// The `extras` JSON as it appears in the file
+    pub extras: Option<Box<RawValue>>,
+    /// Index of the object's packet in `Resources::xmp_packets`
+    pub xmp_packet: Option<usize>,
+}
+
+impl Metadata {
+    pub fn new(extras: &Extras, extensions: Option<&Map<String, Value>>) -> Self {
+        let xmp_packet = extensions
+            .and_then(|extensions| extensions.get(KHR_XMP_JSON_LD))
+            .and_then(|xmp| xmp.get("packet"))
+            .and_then(Value::as_u64)
+            .map(|packet| packet as usize);
+
+        Self {
+            extras: extras.clone(),
+            xmp_packet,
+        }
+    }
+
+    /// Parses the `extras`, or returns `None` if there are none.
+    pub fn extras(&self) -> Option<Value> {
+        self.extras
+            .as_ref()
+            .and_then(|extras| serde_json::from_str(extras.get()).ok())
+    }
+
+    /// Looks up `key` in the `extras` object, such as a part ID.
+    pub fn extra(&self, key: &str) -> Option<Value> {
+        match self.extras()? {
+            Value::Object(mut extras) => extras.remove(key),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the `KHR_xmp_json_ld` packets, which objects refer to by index.
+pub fn read_xmp_packets(document: &gltf::Document) -> Vec<Value> {
+    document
+        .extensions()
+        .and_then(|extensions| extensions.get(KHR_XMP_JSON_LD))
+        .and_then(|xmp| xmp.get("packets"))
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
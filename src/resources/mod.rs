@@ -1,19 +1,37 @@
-use std::{fmt, path};
+use std::{collections::HashMap, fmt, path};
 
 use anyhow::anyhow;
-use tracing::{debug, info};
+use gltf::{mesh::Mode, Semantic};
+use tracing::{debug, info, warn};
 
+use crate::bounds::Aabb;
 use crate::Result;
 
+mod camera;
+mod compression;
+mod instancing;
 mod material;
 mod mesh;
+mod metadata;
 mod node;
+mod normals;
+mod quantization;
 mod scene;
+mod tangents;
 mod texture;
-
-pub use material::Material;
+mod topology;
+
+pub use camera::{Camera, Projection};
+pub use compression::is_meshopt_fallback;
+pub use material::{
+    Clearcoat, Material, ShadingModel, Sheen, Specular, TextureInfo, TextureTransform,
+    Transmission, Volume,
+};
 pub use mesh::{Mesh, Primitive};
+pub use metadata::Metadata;
 pub use node::Node;
+pub use normals::NormalsMode;
+pub use quantization::{QuantizedAttribute, QuantizedAttributes};
 pub use scene::Scene;
 pub use texture::{Sampler, Texture};
 
@@ -25,15 +43,59 @@ pub type Image = image::RgbaImage;
 
 pub type Buffer = Vec<u8>;
 
+/// Extensions the loader understands, so files requiring them can be loaded.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "KHR_materials_clearcoat",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_sheen",
+    "KHR_materials_specular",
+    "KHR_materials_transmission",
+    "KHR_materials_unlit",
+    "KHR_materials_variants",
+    "KHR_materials_volume",
+    "KHR_mesh_quantization",
+    "KHR_texture_transform",
+    compression::KHR_DRACO_MESH_COMPRESSION,
+    compression::EXT_MESHOPT_COMPRESSION,
+    instancing::EXT_MESH_GPU_INSTANCING,
+    metadata::KHR_XMP_JSON_LD,
+];
+
+/// Fails with the offending names if the document requires extensions that
+/// the loader doesn't support.
+pub fn check_required_extensions(document: &gltf::Document) -> Result<()> {
+    let unsupported = document
+        .extensions_required()
+        .filter(|name| !SUPPORTED_EXTENSIONS.contains(name))
+        .collect::<Vec<_>>();
+
+    if !unsupported.is_empty() {
+        return Err(anyhow!(
+            "Required glTF extensions are not supported: {}",
+            unsupported.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
 pub struct Resources {
     pub scenes: Vec<Scene>,
     pub nodes: Vec<Node>,
+    pub cameras: Vec<Camera>,
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
     pub textures: Vec<Texture>,
     pub buffers: Vec<Buffer>,
     pub images: Vec<Image>,
     pub default_scene_index: usize,
+    /// Names of the `KHR_materials_variants` variants
+    pub variants: Vec<String>,
+    /// `KHR_xmp_json_ld` packets, referred to by `Metadata::xmp_packet`
+    pub xmp_packets: Vec<serde_json::Value>,
+    /// Names of the animations, which aren't played back
+    pub animations: Vec<Option<String>>,
 }
 
 impl fmt::Debug for Resources {
@@ -41,17 +103,35 @@ impl fmt::Debug for Resources {
         f.debug_struct("Resources")
             .field("scenes", &self.scenes.len())
             .field("nodes", &self.nodes.len())
+            .field("cameras", &self.cameras.len())
             .field("meshes", &self.meshes.len())
             .field("materials", &self.materials.len())
             .field("textures", &self.textures.len())
             .field("images", &self.images.len())
             .field("default_scene_index", &self.default_scene_index)
+            .field("variants", &self.variants)
+            .field("xmp_packets", &self.xmp_packets.len())
+            .field("animations", &self.animations.len())
             .finish()
     }
 }
 
+/// Options controlling how a glTF file is turned into `Resources`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadOptions {
+    /// How to generate normals for primitives that lack them.
+    pub normals: NormalsMode,
+}
+
 impl Resources {
     pub async fn load_gltf<P: AsRef<path::Path> + fmt::Debug>(path: P) -> Result<Resources> {
+        Self::load_gltf_with_options(path, LoadOptions::default()).await
+    }
+
+    pub async fn load_gltf_with_options<P: AsRef<path::Path> + fmt::Debug>(
+        path: P,
+        options: LoadOptions,
+    ) -> Result<Resources> {
         let (gltf, buffers, images) = import_gltf(path).await?;
 
         let mut textures = vec![];
@@ -84,31 +164,59 @@ impl Resources {
             );
 
             let name = material.name().map(str::to_owned);
+            let shading_model = if material.unlit() {
+                ShadingModel::Unlit
+            } else {
+                ShadingModel::Lit
+            };
             let pbr = material.pbr_metallic_roughness();
             let base_color_factor = pbr.base_color_factor();
             let metallic_factor = pbr.metallic_factor();
             let roughness_factor = pbr.roughness_factor();
 
-            let base_color_texture_index = pbr
-                .base_color_texture()
-                .map(|texture_info| texture_info.texture().index());
+            let base_color_texture = pbr.base_color_texture().map(TextureInfo::from);
+
+            let metallic_roughness_texture =
+                pbr.metallic_roughness_texture().map(TextureInfo::from);
+
+            let normal_texture = material.normal_texture().map(TextureInfo::from);
 
-            let metallic_roughness_texture_index = pbr
-                .metallic_roughness_texture()
-                .map(|texture_info| texture_info.texture().index());
+            let emissive_factor = material.emissive_factor();
+            let emissive_texture = material.emissive_texture().map(TextureInfo::from);
+            let emissive_strength = material.emissive_strength().unwrap_or(1.0);
 
-            let normal_texture_index = material
-                .normal_texture()
-                .map(|texture_info| texture_info.texture().index());
+            let clearcoat = material
+                .extension_value("KHR_materials_clearcoat")
+                .map(Clearcoat::from_extension);
+            let sheen = material
+                .extension_value("KHR_materials_sheen")
+                .map(Sheen::from_extension);
+            let transmission = material.transmission().map(Transmission::from);
+            let volume = material.volume().map(Volume::from);
+            let ior = material.ior().unwrap_or(1.5);
+            let specular = material.specular().map(Specular::from);
+
+            let metadata = Metadata::new(material.extras(), material.extensions());
 
             let material = Material {
                 name,
+                shading_model,
                 base_color_factor,
-                base_color_texture_index,
-                normal_texture_index,
+                base_color_texture,
+                normal_texture,
                 metallic_factor,
                 roughness_factor,
-                metallic_roughness_texture_index,
+                metallic_roughness_texture,
+                emissive_factor,
+                emissive_texture,
+                emissive_strength,
+                clearcoat,
+                sheen,
+                transmission,
+                volume,
+                ior,
+                specular,
+                metadata,
             };
 
             materials.push(material);
@@ -126,57 +234,194 @@ impl Resources {
             let mut primitives = vec![];
 
             for primitive in mesh.primitives() {
-                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let get_buffer_data =
+                    |buffer: gltf::Buffer| Some(buffers[buffer.index()].as_slice());
+                let reader = primitive.reader(get_buffer_data);
+
+                // Integer attributes are read by hand, since the reader only
+                // understands the types allowed without `KHR_mesh_quantization`
+                let quantized = |semantic| {
+                    primitive
+                        .get(&semantic)
+                        .and_then(|accessor| QuantizedAttribute::read(accessor, get_buffer_data))
+                };
 
-                let positions = reader
-                    .read_positions()
-                    .map(|iter| iter.collect::<Vec<_>>())
-                    .ok_or_else(|| anyhow!("No positions found"))?;
+                let quantized_positions = quantized(Semantic::Positions);
+                let quantized_normals = quantized(Semantic::Normals);
+                let quantized_tangents = quantized(Semantic::Tangents);
+                let quantized_tex_coords = quantized(Semantic::TexCoords(0));
+                let quantized_tex_coords_1 = quantized(Semantic::TexCoords(1));
+
+                let positions = match &quantized_positions {
+                    Some(positions) => positions.dequantize(),
+                    None => reader
+                        .read_positions()
+                        .map(|iter| iter.collect::<Vec<_>>())
+                        .ok_or_else(|| anyhow!("No positions found"))?,
+                };
 
                 debug!("Found {} positions", positions.len());
 
-                let tex_coords = reader
-                    .read_tex_coords(0)
-                    .map(|iter| iter.into_f32().collect::<Vec<_>>())
-                    .unwrap_or_else(|| {
-                        debug!("No tex coords found, using default");
-                        vec![[0.0, 0.0]; positions.len()]
-                    });
+                let tex_coords = match &quantized_tex_coords {
+                    Some(tex_coords) => tex_coords.dequantize(),
+                    None => reader
+                        .read_tex_coords(0)
+                        .map(|iter| iter.into_f32().collect::<Vec<_>>())
+                        .unwrap_or_else(|| {
+                            debug!("No tex coords found, using default");
+                            vec![[0.0, 0.0]; positions.len()]
+                        }),
+                };
 
                 debug!("Found {} tex coords", tex_coords.len());
 
-                let normals = reader
-                    .read_normals()
-                    .map(|iter| iter.collect::<Vec<_>>())
-                    .ok_or_else(|| anyhow!("No normals found"))?;
+                let tex_coords_1 = match &quantized_tex_coords_1 {
+                    Some(tex_coords) => tex_coords.dequantize(),
+                    None => reader
+                        .read_tex_coords(1)
+                        .map(|iter| iter.into_f32().collect::<Vec<_>>())
+                        .unwrap_or_else(|| vec![[0.0, 0.0]; positions.len()]),
+                };
 
-                debug!("Found {} normals", normals.len());
+                let colors = reader
+                    .read_colors(0)
+                    .map(|iter| iter.into_rgba_f32().collect::<Vec<_>>())
+                    .unwrap_or_else(|| vec![[1.0, 1.0, 1.0, 1.0]; positions.len()]);
 
-                let tangents = reader
-                    .read_tangents()
-                    .map(|iter| iter.collect::<Vec<_>>())
-                    .ok_or_else(|| anyhow!("No tangents found"))?;
+                let normals = match &quantized_normals {
+                    Some(normals) => Some(normals.dequantize()),
+                    None => reader.read_normals().map(|iter| iter.collect::<Vec<_>>()),
+                };
 
-                let indices = reader
-                    .read_indices()
-                    .map(|iter| iter.into_u32().collect::<Vec<_>>())
-                    .ok_or_else(|| anyhow!("No indices found"))?;
+                if let Some(normals) = &normals {
+                    debug!("Found {} normals", normals.len());
+                }
 
-                debug!("Found {} indices", indices.len());
+                let tangents = match &quantized_tangents {
+                    Some(tangents) => Some(tangents.dequantize()),
+                    None => reader.read_tangents().map(|iter| iter.collect::<Vec<_>>()),
+                };
 
-                let material_index = primitive.material().index().unwrap();
+                // Non-normalized tex coords would need scaling on the GPU, and
+                // are rare enough to just widen
+                let quantized = QuantizedAttributes {
+                    positions: quantized_positions,
+                    normals: quantized_normals,
+                    tex_coords: quantized_tex_coords.filter(|tex_coords| tex_coords.normalized),
+                    tex_coords_1: quantized_tex_coords_1.filter(|tex_coords| tex_coords.normalized),
+                };
 
-                primitives.push(Primitive {
+                let indices = reader
+                    .read_indices()
+                    .map(|iter| iter.into_u32().collect::<Vec<_>>());
+
+                match &indices {
+                    Some(indices) => debug!("Found {} indices", indices.len()),
+                    None => debug!("No indices found, drawing non-indexed"),
+                }
+
+                let mode = primitive.mode();
+
+                let material_index = primitive.material().index();
+
+                let material_variants = primitive
+                    .mappings()
+                    .flat_map(|mapping| {
+                        let material_index = mapping.material().index();
+                        mapping
+                            .variants()
+                            .iter()
+                            .filter_map(move |&variant| Some((variant as usize, material_index?)))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                // Tangents follow the first material, including variants, with a normal texture
+                let normal_texture_tex_coord = std::iter::once(primitive.material())
+                    .chain(primitive.mappings().map(|mapping| mapping.material()))
+                    .find_map(|material| material.normal_texture())
+                    .map(|normal_texture| TextureInfo::from(normal_texture).tex_coord);
+
+                let bounds = primitive
+                    .get(&Semantic::Positions)
+                    .and_then(|accessor| mesh::accessor_bounds(&accessor))
+                    .unwrap_or_else(|| {
+                        debug!("No position bounds found, computing them");
+                        Aabb::from_points(positions.iter().copied().map(glam::Vec3::from))
+                    });
+
+                let mut primitive = Primitive {
                     positions,
                     tex_coords,
-                    normals,
-                    tangents,
+                    normals: normals.unwrap_or_default(),
+                    tangents: tangents.unwrap_or_default(),
+                    colors,
+                    tex_coords_1,
                     indices,
+                    mode,
                     material_index,
-                });
+                    material_variants,
+                    quantized,
+                    bounds,
+                };
+
+                let is_triangles = matches!(
+                    mode,
+                    Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
+                );
+
+                // wgpu has no triangle fans, and normal and tangent generation
+                // work on triangle lists, so unroll those first.
+                if mode == Mode::TriangleFan
+                    || (mode == Mode::TriangleStrip
+                        && (primitive.normals.is_empty() || primitive.tangents.is_empty()))
+                {
+                    primitive.unroll_triangles();
+                }
+
+                // Likewise, there are no line loops in wgpu.
+                primitive.close_line_loop();
+
+                if primitive.normals.is_empty() {
+                    if is_triangles {
+                        warn!(mode = ?options.normals, "No normals found, generating");
+                        primitive.generate_normals(options.normals);
+                    } else {
+                        debug!(?mode, "No normals found, using defaults");
+                        primitive.normals = vec![[0.0, 0.0, 1.0]; primitive.positions.len()];
+                    }
+                }
+
+                if primitive.tangents.is_empty() {
+                    match normal_texture_tex_coord.filter(|_| is_triangles) {
+                        Some(tex_coord) => {
+                            info!("No tangents found, generating MikkTSpace tangents");
+                            if !primitive.generate_tangents(tex_coord) {
+                                warn!("Failed to generate tangents, using defaults");
+                                primitive.generate_default_tangents();
+                            }
+                        }
+                        None => {
+                            debug!("No tangents found and no normal texture, using defaults");
+                            primitive.generate_default_tangents();
+                        }
+                    }
+                }
+
+                primitives.push(primitive);
             }
 
-            meshes.push(Mesh { name, primitives });
+            let metadata = Metadata::new(mesh.extras(), mesh.extensions());
+
+            let bounds = primitives.iter().fold(Aabb::EMPTY, |bounds, primitive| {
+                bounds.union(&primitive.bounds)
+            });
+
+            meshes.push(Mesh {
+                name,
+                primitives,
+                metadata,
+                bounds,
+            });
         }
 
         info!(meshes = meshes.len(), "Loaded meshes");
@@ -196,20 +441,38 @@ impl Resources {
 
             let mesh_index = node.mesh().map(|mesh| mesh.index());
 
+            let camera_index = node.camera().map(|camera| camera.index());
+
             let transform = node.transform().matrix();
 
             let transform = glam::Mat4::from_cols_array_2d(&transform);
 
+            let get_buffer_data = |buffer: gltf::Buffer| Some(buffers[buffer.index()].as_slice());
+            let instances = instancing::read_instances(&node, &gltf, get_buffer_data)?;
+
+            if let Some(instances) = &instances {
+                debug!("Found {} instances", instances.len());
+            }
+
+            let metadata = Metadata::new(node.extras(), node.extensions());
+
             nodes.push(Node {
                 name,
                 children,
                 mesh_index,
+                camera_index,
                 transform,
+                instances,
+                metadata,
             });
         }
 
         info!(nodes = nodes.len(), "Loaded nodes");
 
+        let cameras: Vec<Camera> = gltf.cameras().map(Camera::from).collect();
+
+        info!(cameras = cameras.len(), "Loaded cameras");
+
         let mut scenes = vec![];
 
         for scene in gltf.scenes() {
@@ -223,22 +486,64 @@ impl Resources {
                 nodes.push(node.index());
             }
 
-            scenes.push(Scene { name, nodes });
+            let metadata = Metadata::new(scene.extras(), scene.extensions());
+
+            scenes.push(Scene {
+                name,
+                nodes,
+                metadata,
+            });
         }
 
         let default_scene_index = gltf.default_scene().map(|scene| scene.index()).unwrap_or(0);
 
+        let variants: Vec<String> = gltf
+            .variants()
+            .map(|variants| variants.map(|variant| variant.name().to_owned()).collect())
+            .unwrap_or_default();
+
+        info!(?variants, "Loaded material variants");
+
+        let xmp_packets = metadata::read_xmp_packets(&gltf);
+
+        let animations: Vec<Option<String>> = gltf
+            .animations()
+            .map(|animation| animation.name().map(str::to_owned))
+            .collect();
+
         Ok(Resources {
             scenes,
             nodes,
+            cameras,
             meshes,
             materials,
             textures,
             buffers,
             images,
             default_scene_index,
+            variants,
+            xmp_packets,
+            animations,
         })
     }
+
+    /// Indices of the nodes whose `extras` map `key` to `value`, such as
+    /// all nodes with a given part ID.
+    pub fn find_nodes_by_extra(&self, key: &str, value: &serde_json::Value) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.metadata.extra(key).as_ref() == Some(value))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The `KHR_xmp_json_ld` packet of an object.
+    pub fn xmp_packet(&self, metadata: &Metadata) -> Option<&serde_json::Value> {
+        metadata
+            .xmp_packet
+            .and_then(|index| self.xmp_packets.get(index))
+    }
 }
 
 pub async fn import_gltf<P>(path: P) -> Result<(gltf::Document, Vec<Buffer>, Vec<Image>)>
@@ -246,34 +551,58 @@ where
     P: AsRef<path::Path>,
 {
     #[cfg(target_arch = "wasm32")]
-    {
-        crate::wasm::import_gltf(path).await.map_err(|e| {
-            tracing::error!("Failed to fetch gltf: {:?}", e);
-            anyhow!("Failed to fetch gltf: {:?}", e)
-        })
-    }
+    let (document, buffers, images) = crate::wasm::import_gltf(path).await.map_err(|e| {
+        tracing::error!("Failed to fetch gltf: {:?}", e);
+        anyhow!("Failed to fetch gltf: {:?}", e)
+    })?;
 
     #[cfg(not(target_arch = "wasm32"))]
-    {
-        let (gltf, buffers, images) = gltf::import(path)?;
+    let (document, buffers, images) = {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or_else(|| path::Path::new("./"));
+
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_reader_without_validation(reader)?;
+
+        check_required_extensions(&document)?;
+
+        let buffers = document
+            .buffers()
+            .map(|buffer| {
+                if is_meshopt_fallback(&buffer) {
+                    return Ok(gltf::buffer::Data(Vec::new()));
+                }
+                gltf::buffer::Data::from_source_and_blob(buffer.source(), Some(base), &mut blob)
+            })
+            .collect::<gltf::Result<Vec<_>>>()?;
+
+        let images = gltf::import_images(&document, Some(base), &buffers)?
+            .into_iter()
+            .map(|image| {
+                use crate::ext::RgbaImageExt;
+                image::RgbaImage::from_gltf_image(image)
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         let buffers = buffers
             .into_iter()
             .map(|buffer| buffer.0)
             .collect::<Vec<_>>();
 
-        let images = images
-            .into_iter()
-            .map(|image| {
-                let image = {
-                    use crate::ext::RgbaImageExt;
-                    image::RgbaImage::from_gltf_image(image)
-                        .ok_or(anyhow!("Failed to convert gltf image to rgba"))?
-                };
-                Ok(image)
-            })
-            .collect::<Result<Vec<_>>>()?;
+        (document, buffers, images)
+    };
 
-        Ok((gltf, buffers, images))
-    }
+    let (document, buffers) = compression::decompress(document, buffers)?;
+
+    // Required extensions were checked above, but gltf only knows a few of them
+    let mut json = document.into_json();
+    let extensions_required = std::mem::take(&mut json.extensions_required);
+    let mut json = gltf::Document::from_json(json)?.into_json();
+    json.extensions_required = extensions_required;
+
+    Ok((
+        gltf::Document::from_json_without_validation(json),
+        buffers,
+        images,
+    ))
 }
@@ -1,7 +1,14 @@
+use super::Metadata;
+
 #[derive(Debug)]
 pub struct Node {
     pub name: Option<String>,
     pub mesh_index: Option<usize>,
+    pub camera_index: Option<usize>,
     pub children: Vec<usize>,
     pub transform: glam::Mat4,
+    /// Local transforms of the `EXT_mesh_gpu_instancing` instances, each
+    /// applied before `transform`
+    pub instances: Option<Vec<glam::Mat4>>,
+    pub metadata: Metadata,
 }
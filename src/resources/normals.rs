@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use tracing::warn;
+
+use super::Primitive;
+
+/// How normals are generated for primitives that don't provide them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NormalsMode {
+    /// One normal per triangle, as required by the glTF spec.
+    #[default]
+    Flat,
+    /// Averages the normals of adjacent triangles that meet at an angle
+    /// below `angle_threshold` (in degrees), keeping harder edges sharp.
+    Smooth { angle_threshold: f32 },
+}
+
+impl Primitive {
+    /// Generates normals for a triangle list primitive.
+    ///
+    /// Every triangle corner gets its own vertex, so the primitive is unwelded
+    /// and becomes non-indexed.
+    pub fn generate_normals(&mut self, mode: NormalsMode) {
+        let mut indices = self.vertex_indices();
+
+        // A trailing partial triangle has no face to take a normal from
+        let remainder = indices.len() % 3;
+        if remainder != 0 {
+            warn!(
+                count = indices.len(),
+                "Index count is not a multiple of 3, dropping the last partial triangle"
+            );
+            indices.truncate(indices.len() - remainder);
+        }
+
+        let positions: Vec<glam::Vec3> = indices
+            .iter()
+            .map(|&index| glam::Vec3::from(self.positions[index as usize]))
+            .collect();
+
+        // Area-weighted face normals, one per triangle
+        let face_normals: Vec<glam::Vec3> = positions
+            .chunks_exact(3)
+            .map(|triangle| (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]))
+            .collect();
+
+        let normals: Vec<glam::Vec3> = match mode {
+            NormalsMode::Flat => face_normals
+                .iter()
+                .flat_map(|&normal| [normal; 3])
+                .collect(),
+            NormalsMode::Smooth { angle_threshold } => {
+                let cos_threshold = angle_threshold.to_radians().cos();
+
+                // Group corners by position rather than by index, since exporters
+                // split vertices along UV seams.
+                let mut corners_by_position: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+                for (corner, position) in positions.iter().enumerate() {
+                    corners_by_position
+                        .entry(position.to_array().map(f32::to_bits))
+                        .or_default()
+                        .push(corner);
+                }
+
+                positions
+                    .iter()
+                    .enumerate()
+                    .map(|(corner, position)| {
+                        let direction = face_normals[corner / 3].normalize_or_zero();
+
+                        corners_by_position[&position.to_array().map(f32::to_bits)]
+                            .iter()
+                            .map(|&other| face_normals[other / 3])
+                            .filter(|other| {
+                                other.normalize_or_zero().dot(direction) >= cos_threshold
+                            })
+                            .sum()
+                    })
+                    .collect()
+            }
+        };
+
+        self.normals = normals
+            .into_iter()
+            .map(|normal| normal.try_normalize().unwrap_or(glam::Vec3::Z).to_array())
+            .collect();
+        self.positions = unweld(&self.positions, &indices);
+        self.tex_coords = unweld(&self.tex_coords, &indices);
+        self.tangents = unweld(&self.tangents, &indices);
+        self.colors = unweld(&self.colors, &indices);
+        self.tex_coords_1 = unweld(&self.tex_coords_1, &indices);
+        for quantized in [
+            &mut self.quantized.positions,
+            &mut self.quantized.tex_coords,
+            &mut self.quantized.tex_coords_1,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *quantized = quantized.unweld(&indices);
+        }
+        // The normals are regenerated, so they are no longer quantized
+        self.quantized.normals = None;
+        self.indices = None;
+    }
+}
+
+fn unweld<T: Copy>(values: &[T], indices: &[u32]) -> Vec<T> {
+    if values.is_empty() {
+        return vec![];
+    }
+
+    indices
+        .iter()
+        .map(|&index| values[index as usize])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    /// Two triangles sharing the edge from the origin to +X, folded by 90°
+    /// so that one faces +Z and the other -Y.
+    fn hinge() -> Primitive {
+        Primitive::from_positions(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, -1.0],
+            ],
+            Some(vec![0, 1, 2, 1, 0, 3]),
+        )
+    }
+
+    fn assert_normals(primitive: &Primitive, expected: &[glam::Vec3]) {
+        assert_eq!(primitive.normals.len(), expected.len());
+        for (normal, expected) in primitive.normals.iter().zip(expected) {
+            assert!(
+                glam::Vec3::from(*normal).abs_diff_eq(*expected, EPSILON),
+                "{normal:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn flat_normals_are_face_normals() {
+        let mut primitive = hinge();
+        primitive.generate_normals(NormalsMode::Flat);
+
+        let (z, y) = (glam::Vec3::Z, glam::Vec3::NEG_Y);
+        assert_normals(&primitive, &[z, z, z, y, y, y]);
+        assert_eq!(primitive.indices, None);
+        assert_eq!(primitive.positions[3], [1.0, 0.0, 0.0]);
+        assert_eq!(primitive.positions[5], [0.0, 0.0, -1.0]);
+    }
+
+    #[test]
+    fn smooth_normals_average_below_threshold() {
+        let mut primitive = hinge();
+        primitive.generate_normals(NormalsMode::Smooth {
+            angle_threshold: 100.0,
+        });
+
+        // Only the shared edge is averaged
+        let shared = glam::Vec3::new(0.0, -1.0, 1.0).normalize();
+        assert_normals(
+            &primitive,
+            &[
+                shared,
+                shared,
+                glam::Vec3::Z,
+                shared,
+                shared,
+                glam::Vec3::NEG_Y,
+            ],
+        );
+    }
+
+    #[test]
+    fn smooth_normals_split_above_threshold() {
+        let mut primitive = hinge();
+        primitive.generate_normals(NormalsMode::Smooth {
+            angle_threshold: 60.0,
+        });
+
+        let (z, y) = (glam::Vec3::Z, glam::Vec3::NEG_Y);
+        assert_normals(&primitive, &[z, z, z, y, y, y]);
+    }
+
+    #[test]
+    fn non_indexed_partial_triangle_is_dropped() {
+        let mut primitive = Primitive::from_positions(
+            vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+            None,
+        );
+        primitive.generate_normals(NormalsMode::Flat);
+
+        assert_normals(&primitive, &[glam::Vec3::Z; 3]);
+        assert_eq!(primitive.positions.len(), 3);
+        assert_eq!(primitive.indices, None);
+    }
+}
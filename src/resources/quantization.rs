@@ -0,0 +1,220 @@
+use gltf::accessor::{DataType, Dimensions, Item, Iter};
+
+/// A vertex attribute kept in its compact `KHR_mesh_quantization` integer
+/// form, so it can be uploaded without widening it to `f32`.
+#[derive(Clone, Debug)]
+pub struct QuantizedAttribute {
+    /// One of `I8`, `U8`, `I16` or `U16`
+    pub data_type: DataType,
+    pub normalized: bool,
+    /// Components per vertex in `data`. Unused trailing components are zero,
+    /// so that every vertex is four-byte aligned.
+    pub components: usize,
+    pub data: Vec<u8>,
+}
+
+/// The attributes of a primitive that can stay quantized on the GPU.
+#[derive(Clone, Debug, Default)]
+pub struct QuantizedAttributes {
+    pub positions: Option<QuantizedAttribute>,
+    pub normals: Option<QuantizedAttribute>,
+    pub tex_coords: Option<QuantizedAttribute>,
+    pub tex_coords_1: Option<QuantizedAttribute>,
+}
+
+impl QuantizedAttribute {
+    /// Reads an integer accessor, or returns `None` if it holds floats.
+    pub fn read<'a, 's, F>(accessor: gltf::Accessor<'a>, get_buffer_data: F) -> Option<Self>
+    where
+        F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+    {
+        let data_type = accessor.data_type();
+        let normalized = accessor.normalized();
+
+        let (components, data) = match data_type {
+            DataType::I8 => (4, read_padded::<i8, _>(accessor, get_buffer_data, 4)?),
+            DataType::U8 => (4, read_padded::<u8, _>(accessor, get_buffer_data, 4)?),
+            DataType::I16 | DataType::U16 => {
+                let components = match accessor.dimensions() {
+                    Dimensions::Vec2 => 2,
+                    _ => 4,
+                };
+                let data = match data_type {
+                    DataType::I16 => read_padded::<i16, _>(accessor, get_buffer_data, components)?,
+                    _ => read_padded::<u16, _>(accessor, get_buffer_data, components)?,
+                };
+                (components, data)
+            }
+            DataType::U32 | DataType::F32 => return None,
+        };
+
+        Some(Self {
+            data_type,
+            normalized,
+            components,
+            data,
+        })
+    }
+
+    fn component_size(&self) -> usize {
+        self.data_type.size()
+    }
+
+    fn vertex_size(&self) -> usize {
+        self.components * self.component_size()
+    }
+
+    /// The largest value of the integer type, which a normalized vertex
+    /// format maps to 1.
+    fn max(&self) -> f32 {
+        match self.data_type {
+            DataType::I8 => i8::MAX as f32,
+            DataType::U8 => u8::MAX as f32,
+            DataType::I16 => i16::MAX as f32,
+            DataType::U16 => u16::MAX as f32,
+            DataType::U32 | DataType::F32 => 1.0,
+        }
+    }
+
+    /// How much larger the actual values are than what a normalized vertex
+    /// format reads: 1 for normalized data, the integer range otherwise.
+    pub fn scale(&self) -> f32 {
+        if self.normalized {
+            1.0
+        } else {
+            self.max()
+        }
+    }
+
+    /// Converts the first `N` components of every vertex to floats, following
+    /// the glTF rules for normalized integers.
+    pub fn dequantize<const N: usize>(&self) -> Vec<[f32; N]> {
+        let max = self.max();
+
+        self.data
+            .chunks_exact(self.vertex_size())
+            .map(|vertex| {
+                std::array::from_fn(|component| {
+                    let bytes = &vertex[component * self.component_size()..];
+                    let value = match self.data_type {
+                        DataType::I8 => bytes[0] as i8 as f32,
+                        DataType::U8 => bytes[0] as f32,
+                        DataType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+                        _ => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+                    };
+
+                    if self.normalized {
+                        (value / max).max(-1.0)
+                    } else {
+                        value
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Gives every index its own copy of the vertex it refers to.
+    pub fn unweld(&self, indices: &[u32]) -> Self {
+        let vertex_size = self.vertex_size();
+
+        let data = indices
+            .iter()
+            .flat_map(|&index| {
+                let start = index as usize * vertex_size;
+                &self.data[start..start + vertex_size]
+            })
+            .copied()
+            .collect();
+
+        Self {
+            data,
+            ..self.clone()
+        }
+    }
+}
+
+fn read_padded<'a, 's, T, F>(
+    accessor: gltf::Accessor<'a>,
+    get_buffer_data: F,
+    components: usize,
+) -> Option<Vec<u8>>
+where
+    T: Item + Copy + bytemuck::Pod,
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    fn pad<T: Item + Copy, const N: usize>(
+        values: impl Iterator<Item = [T; N]>,
+        components: usize,
+    ) -> Vec<T> {
+        values
+            .flat_map(|value| {
+                (0..components).map(move |i| value.get(i).copied().unwrap_or(T::zero()))
+            })
+            .collect()
+    }
+
+    let values: Vec<T> = match accessor.dimensions() {
+        Dimensions::Vec2 => pad(Iter::<[T; 2]>::new(accessor, get_buffer_data)?, components),
+        Dimensions::Vec3 => pad(Iter::<[T; 3]>::new(accessor, get_buffer_data)?, components),
+        Dimensions::Vec4 => pad(Iter::<[T; 4]>::new(accessor, get_buffer_data)?, components),
+        _ => return None,
+    };
+
+    Some(bytemuck::cast_slice(&values).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a `VEC2` `UNSIGNED_SHORT` accessor whose second element is
+    /// replaced through `sparse`, optionally on top of a base buffer view.
+    fn read_sparse(base: bool) -> Vec<u16> {
+        let mut buffer = bytemuck::cast_slice::<u16, u8>(&[1, 2, 3, 4, 5, 6]).to_vec();
+        buffer.extend(bytemuck::cast_slice::<u16, u8>(&[1, 0, 7, 8]));
+
+        let mut accessor = serde_json::json!({
+            "componentType": 5123,
+            "normalized": true,
+            "count": 3,
+            "type": "VEC2",
+            "sparse": {
+                "count": 1,
+                "indices": { "bufferView": 1, "componentType": 5123 },
+                "values": { "bufferView": 2 },
+            },
+        });
+        if base {
+            accessor["bufferView"] = 0.into();
+        }
+
+        let root = gltf::json::deserialize::from_value(serde_json::json!({
+            "asset": { "version": "2.0" },
+            "accessors": [accessor],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 12 },
+                { "buffer": 0, "byteOffset": 12, "byteLength": 2 },
+                { "buffer": 0, "byteOffset": 16, "byteLength": 4 },
+            ],
+            "buffers": [{ "byteLength": buffer.len() }],
+        }))
+        .unwrap();
+        let document = gltf::Document::from_json_without_validation(root);
+
+        let accessor = document.accessors().next().unwrap();
+        let attribute = QuantizedAttribute::read(accessor, |_| Some(buffer.as_slice())).unwrap();
+
+        assert_eq!(attribute.components, 2);
+        bytemuck::pod_collect_to_vec(&attribute.data)
+    }
+
+    #[test]
+    fn reads_sparse_accessors() {
+        assert_eq!(read_sparse(true), [1, 2, 7, 8, 5, 6]);
+    }
+
+    #[test]
+    fn reads_sparse_accessors_without_a_buffer_view() {
+        assert_eq!(read_sparse(false), [0, 0, 7, 8, 0, 0]);
+    }
+}
@@ -1,4 +1,7 @@
+use super::Metadata;
+
 pub struct Scene {
     pub name: Option<String>,
     pub nodes: Vec<usize>,
+    pub metadata: Metadata,
 }
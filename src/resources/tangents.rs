@@ -0,0 +1,73 @@
+use super::Primitive;
+
+impl Primitive {
+    /// Generates MikkTSpace tangents for a triangle list primitive from its
+    /// positions, normals and the `TEXCOORD_n` set used by its normal texture.
+    ///
+    /// Returns `false` if the geometry is unsuitable for tangent generation.
+    pub fn generate_tangents(&mut self, tex_coord: u32) -> bool {
+        self.tangents = vec![[0.0, 0.0, 0.0, 1.0]; self.positions.len()];
+        bevy_mikktspace::generate_tangents(&mut Geometry(self, tex_coord))
+    }
+
+    /// Fills in arbitrary tangents perpendicular to the normals.
+    ///
+    /// Cheap, and good enough as long as there is no normal map to orient.
+    pub fn generate_default_tangents(&mut self) {
+        self.tangents = self
+            .normals
+            .iter()
+            .map(|&normal| {
+                glam::Vec3::from(normal)
+                    .any_orthonormal_vector()
+                    .extend(1.0)
+                    .to_array()
+            })
+            .collect();
+    }
+}
+
+struct Geometry<'a>(&'a mut Primitive, u32);
+
+impl Geometry<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        match &self.0.indices {
+            Some(indices) => indices[face * 3 + vert] as usize,
+            None => face * 3 + vert,
+        }
+    }
+}
+
+impl bevy_mikktspace::Geometry for Geometry<'_> {
+    fn num_faces(&self) -> usize {
+        match &self.0.indices {
+            Some(indices) => indices.len() / 3,
+            None => self.0.positions.len() / 3,
+        }
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0.positions[self.index(face, vert)]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.0.normals[self.index(face, vert)]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let tex_coords = match self.1 {
+            1 => &self.0.tex_coords_1,
+            _ => &self.0.tex_coords,
+        };
+        tex_coords[self.index(face, vert)]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.index(face, vert);
+        self.0.tangents[index] = tangent;
+    }
+}
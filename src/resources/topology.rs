@@ -0,0 +1,108 @@
+use gltf::mesh::Mode;
+
+use super::Primitive;
+
+impl Primitive {
+    /// Converts a triangle strip or fan into an indexed triangle list,
+    /// following the winding order defined by the glTF spec.
+    pub fn unroll_triangles(&mut self) {
+        let indices = self.vertex_indices();
+
+        let triangles: Vec<[u32; 3]> = match self.mode {
+            Mode::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, window)| {
+                    if i % 2 == 0 {
+                        [window[0], window[1], window[2]]
+                    } else {
+                        [window[1], window[0], window[2]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => indices
+                .windows(2)
+                .skip(1)
+                .map(|window| [window[0], window[1], indices[0]])
+                .collect(),
+            _ => return,
+        };
+
+        self.indices = Some(triangles.into_iter().flatten().collect());
+        self.mode = Mode::Triangles;
+    }
+
+    /// Converts a line loop into a line strip by repeating its first vertex.
+    pub fn close_line_loop(&mut self) {
+        if self.mode != Mode::LineLoop {
+            return;
+        }
+
+        let mut indices = self.vertex_indices();
+        if let Some(&first) = indices.first() {
+            indices.push(first);
+        }
+
+        self.indices = Some(indices);
+        self.mode = Mode::LineStrip;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primitive(mode: Mode, vertex_count: usize, indices: Option<Vec<u32>>) -> Primitive {
+        let mut primitive = Primitive::from_positions(vec![[0.0; 3]; vertex_count], indices);
+        primitive.mode = mode;
+        primitive
+    }
+
+    #[test]
+    fn unroll_triangle_strip() {
+        let mut strip = primitive(Mode::TriangleStrip, 5, None);
+        strip.unroll_triangles();
+        assert_eq!(strip.mode, Mode::Triangles);
+        assert_eq!(strip.indices, Some(vec![0, 1, 2, 2, 1, 3, 2, 3, 4]));
+
+        let mut strip = primitive(Mode::TriangleStrip, 5, Some(vec![4, 3, 2, 1, 0]));
+        strip.unroll_triangles();
+        assert_eq!(strip.mode, Mode::Triangles);
+        assert_eq!(strip.indices, Some(vec![4, 3, 2, 2, 3, 1, 2, 1, 0]));
+    }
+
+    #[test]
+    fn unroll_triangle_fan() {
+        let mut fan = primitive(Mode::TriangleFan, 5, None);
+        fan.unroll_triangles();
+        assert_eq!(fan.mode, Mode::Triangles);
+        assert_eq!(fan.indices, Some(vec![1, 2, 0, 2, 3, 0, 3, 4, 0]));
+
+        let mut fan = primitive(Mode::TriangleFan, 5, Some(vec![4, 3, 2, 1, 0]));
+        fan.unroll_triangles();
+        assert_eq!(fan.mode, Mode::Triangles);
+        assert_eq!(fan.indices, Some(vec![3, 2, 4, 2, 1, 4, 1, 0, 4]));
+    }
+
+    #[test]
+    fn close_line_loop() {
+        let mut line_loop = primitive(Mode::LineLoop, 3, None);
+        line_loop.close_line_loop();
+        assert_eq!(line_loop.mode, Mode::LineStrip);
+        assert_eq!(line_loop.indices, Some(vec![0, 1, 2, 0]));
+
+        let mut line_loop = primitive(Mode::LineLoop, 3, Some(vec![2, 0, 1]));
+        line_loop.close_line_loop();
+        assert_eq!(line_loop.mode, Mode::LineStrip);
+        assert_eq!(line_loop.indices, Some(vec![2, 0, 1, 2]));
+    }
+
+    #[test]
+    fn other_modes_are_untouched() {
+        let mut triangles = primitive(Mode::Triangles, 3, None);
+        triangles.unroll_triangles();
+        triangles.close_line_loop();
+        assert_eq!(triangles.mode, Mode::Triangles);
+        assert_eq!(triangles.indices, None);
+    }
+}
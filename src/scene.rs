@@ -1,19 +1,59 @@
-use crate::{camera::ArcCamera, model::Model};
+use crate::{
+    bookmark::{self, Bookmark},
+    bounds::Aabb,
+    camera::{ArcCamera, Camera, FlyCamera, NodeCamera, OrbitMode, ProjectionMode, View},
+    controls::CameraMotion,
+    depth::DepthMode,
+    material::Material,
+    model::Model,
+    renderer::{Pipelines, RenderPhase},
+    resources::Projection,
+    uniform::{CameraBinding, Uniforms},
+    viewport::{Layout, Viewport, ViewportKind},
+};
+
+/// The camera the scene is viewed through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActiveCamera {
+    #[default]
+    Orbit,
+    Fly,
+    /// Index into `Scene::cameras()` of a glTF camera
+    Node(usize),
+}
 
 pub struct Scene {
     pub models: Vec<Model>,
     pub camera: ArcCamera,
+    pub fly_camera: FlyCamera,
+    pub active_camera: ActiveCamera,
+    /// Saved views, kept across model loads
+    pub bookmarks: Vec<Bookmark>,
+    /// Which way the projections map depth, matching the render pipelines
+    pub depth_mode: DepthMode,
+    /// How the framebuffer is split into viewports
+    pub layout: Layout,
+    /// Distance between the eyes of the stereo layout, in world units
+    pub eye_separation: f32,
+    /// Size of the framebuffer in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Camera uniforms of each viewport, as many as any layout has
+    pub uniforms: Vec<Uniforms<CameraBinding>>,
 }
 
 impl Scene {
     pub const BIND_GROUP_INDEX: u32 = 0;
 
+    /// About the distance between human eyes, in meters like glTF
+    pub const DEFAULT_EYE_SEPARATION: f32 = 0.065;
+
     pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> =
         wgpu::BindGroupLayoutDescriptor {
             label: Some("Scene Uniforms Bind Group Layout"),
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -26,12 +66,34 @@ impl Scene {
     pub fn new(
         width: u32,
         height: u32,
+        depth_mode: DepthMode,
         device: &wgpu::Device,
         layout: &wgpu::BindGroupLayout,
     ) -> Self {
+        let camera = ArcCamera::new(width, height);
+        let fly_camera = FlyCamera::looking_at(camera.eye(), camera.target, 1.0);
+        let uniforms = (0..Layout::MAX_VIEWPORTS)
+            .map(|_| {
+                Uniforms::new(
+                    camera.uniforms_data(camera.aspect_ratio(), depth_mode),
+                    device,
+                    layout,
+                )
+            })
+            .collect();
+
         Self {
             models: vec![],
-            camera: ArcCamera::new(width, height, device, layout),
+            camera,
+            fly_camera,
+            active_camera: ActiveCamera::Orbit,
+            bookmarks: vec![],
+            depth_mode,
+            layout: Layout::Single,
+            eye_separation: Self::DEFAULT_EYE_SEPARATION,
+            width,
+            height,
+            uniforms,
         }
     }
 
@@ -43,27 +105,347 @@ impl Scene {
         self.models.clear();
     }
 
+    /// Names of the material variants of all models.
+    pub fn variants(&self) -> Vec<&str> {
+        let mut variants: Vec<&str> = vec![];
+        for variant in self.models.iter().flat_map(|model| &model.variants) {
+            if !variants.contains(&variant.as_str()) {
+                variants.push(variant);
+            }
+        }
+        variants
+    }
+
+    /// Switches the models to the variant called `name`, see [`Model::set_variant`].
+    ///
+    /// Returns whether any model has the variant.
+    pub fn set_variant(&mut self, name: Option<&str>) -> bool {
+        let mut found = false;
+        for model in &mut self.models {
+            found |= model.set_variant(name);
+        }
+        found
+    }
+
+    /// World space bounds of all models.
+    pub fn bounds(&self) -> Aabb {
+        self.models
+            .iter()
+            .fold(Aabb::EMPTY, |bounds, model| bounds.union(&model.bounds))
+    }
+
+    /// Switches to the orbit camera and points it at all models, see
+    /// [`ArcCamera::frame`].
+    pub fn frame_camera(&mut self, queue: &wgpu::Queue) {
+        self.active_camera = ActiveCamera::Orbit;
+        let bounds = self.bounds();
+        self.camera.frame(&bounds);
+        self.update_camera(queue);
+    }
+
+    /// The glTF cameras of all models.
+    pub fn cameras(&self) -> impl Iterator<Item = &NodeCamera> {
+        self.models.iter().flat_map(|model| &model.cameras)
+    }
+
+    /// Names of the glTF cameras, falling back to their indices.
+    pub fn camera_names(&self) -> Vec<String> {
+        self.cameras()
+            .enumerate()
+            .map(|(index, camera)| camera.name.clone().unwrap_or_else(|| index.to_string()))
+            .collect()
+    }
+
+    /// Finds a glTF camera by name, or by index.
+    pub fn find_camera(&self, name: &str) -> Option<usize> {
+        self.camera_names()
+            .iter()
+            .position(|camera| camera == name)
+            .or_else(|| {
+                name.parse()
+                    .ok()
+                    .filter(|&index| index < self.cameras().count())
+            })
+    }
+
+    /// Views the scene through the glTF camera at `index` of `cameras()`, or
+    /// through the orbit camera if `index` is `None`.
+    ///
+    /// Returns `false`, leaving the camera as it is, if there is no such camera.
+    pub fn set_camera(&mut self, index: Option<usize>, queue: &wgpu::Queue) -> bool {
+        if index.is_some_and(|index| index >= self.cameras().count()) {
+            return false;
+        }
+
+        self.active_camera = match index {
+            Some(index) => ActiveCamera::Node(index),
+            None => ActiveCamera::Orbit,
+        };
+        self.update_camera(queue);
+
+        true
+    }
+
+    /// Switches between the orbit camera and the fly camera, which takes
+    /// over the other's position and viewing direction.
+    pub fn toggle_fly_camera(&mut self, queue: &wgpu::Queue) {
+        match self.active_camera {
+            ActiveCamera::Fly => {
+                // Orbit around the point as far ahead as the orbit camera
+                // was from its target
+                let eye = self.fly_camera.position;
+                let target = eye + self.fly_camera.forward() * self.camera.distance;
+                self.camera.look_at(eye, target);
+                self.camera.fov = self.fly_camera.fov;
+                self.active_camera = ActiveCamera::Orbit;
+            }
+            _ => {
+                // Crosses the model in a few seconds
+                let speed = match self.camera.bounding_sphere {
+                    Some(sphere) => sphere.radius * 0.5,
+                    None => 1.0,
+                };
+                self.fly_camera =
+                    FlyCamera::looking_at(self.camera.eye(), self.camera.target, speed);
+                self.fly_camera.fov = self.camera.fov;
+                self.fly_camera.bounding_sphere = self.camera.bounding_sphere;
+                self.active_camera = ActiveCamera::Fly;
+            }
+        }
+
+        self.update_camera(queue);
+    }
+
+    /// The view of the active camera, as a bookmark called `name`. Cameras
+    /// without a target get one as far ahead as the orbit camera's.
+    pub fn bookmark(&self, name: String) -> Bookmark {
+        let (fov, projection) = match self.active_camera {
+            ActiveCamera::Orbit => return self.camera.bookmark(name),
+            ActiveCamera::Fly => (self.fly_camera.fov, ProjectionMode::Perspective),
+            ActiveCamera::Node(index) => {
+                match self.cameras().nth(index).map(|camera| camera.projection) {
+                    Some(Projection::Perspective { yfov, .. }) => {
+                        (yfov.to_degrees(), ProjectionMode::Perspective)
+                    }
+                    // The orbit camera's orthographic view is as high as its
+                    // field of view at the target
+                    Some(Projection::Orthographic { ymag, .. }) => (
+                        (ymag / self.camera.distance).atan().to_degrees() * 2.0,
+                        ProjectionMode::Orthographic,
+                    ),
+                    None => return self.camera.bookmark(name),
+                }
+            }
+        };
+
+        // The view looks down +Z with +Y up
+        let world = self.active().view_matrix().inverse();
+        let eye = world.transform_point3(glam::Vec3::ZERO);
+        let forward = world.transform_vector3(glam::Vec3::Z).normalize();
+
+        Bookmark {
+            name,
+            eye,
+            target: eye + forward * self.camera.distance,
+            up: world.transform_vector3(glam::Vec3::Y).normalize(),
+            fov,
+            projection,
+        }
+    }
+
+    pub fn find_bookmark(&self, name: &str) -> Option<&Bookmark> {
+        bookmark::find(&self.bookmarks, name)
+    }
+
+    /// Saves the view of the active camera as the bookmark called `name`,
+    /// replacing any bookmark of the same name.
+    pub fn save_bookmark(&mut self, name: String) {
+        let bookmark = self.bookmark(name);
+        bookmark::insert(&mut self.bookmarks, bookmark);
+    }
+
+    /// Switches to the orbit camera and starts moving it from the current
+    /// view to `bookmark`, see [`ArcCamera::go_to`].
+    pub fn go_to_bookmark(&mut self, bookmark: &Bookmark, queue: &wgpu::Queue) {
+        if self.active_camera != ActiveCamera::Orbit {
+            // Set off from what is on screen
+            let current = self.bookmark(String::new());
+            self.camera.restore(&current);
+            self.set_camera(None, queue);
+        }
+        self.camera.go_to(bookmark);
+    }
+
+    /// Switches to the orbit camera and moves it to `bookmark` at once, for
+    /// captures that need the exact view.
+    pub fn restore_bookmark(&mut self, bookmark: &Bookmark, queue: &wgpu::Queue) {
+        self.active_camera = ActiveCamera::Orbit;
+        self.camera.restore(bookmark);
+        self.update_camera(queue);
+    }
+
+    fn active(&self) -> &dyn Camera {
+        match self.active_camera {
+            ActiveCamera::Orbit => &self.camera,
+            ActiveCamera::Fly => &self.fly_camera,
+            ActiveCamera::Node(index) => match self.cameras().nth(index) {
+                Some(camera) => camera,
+                None => &self.camera,
+            },
+        }
+    }
+
+    /// Uploads the matrices of the cameras of each viewport, for after
+    /// changing a camera directly.
+    pub fn update_camera(&mut self, queue: &wgpu::Queue) {
+        let data: Vec<CameraBinding> = self
+            .viewports()
+            .iter()
+            .map(|viewport| self.viewport_uniforms_data(viewport))
+            .collect();
+
+        for (data, uniforms) in data.into_iter().zip(&mut self.uniforms) {
+            uniforms.update(data, queue);
+        }
+    }
+
+    fn viewport_uniforms_data(&self, viewport: &Viewport) -> CameraBinding {
+        let aspect_ratio = viewport.rect.aspect_ratio();
+        let data = match viewport.kind {
+            ViewportKind::Active => self.active().uniforms_data(aspect_ratio, self.depth_mode),
+            ViewportKind::Orthographic(view) => self
+                .camera
+                .side_view(view)
+                .uniforms_data(aspect_ratio, self.depth_mode),
+            // Cameras without a target converge as far ahead as the orbit
+            // camera's
+            ViewportKind::Eye(eye) => self.active().eye_uniforms_data(
+                aspect_ratio,
+                self.depth_mode,
+                eye.side() * self.eye_separation * 0.5,
+                self.camera.distance,
+            ),
+        };
+
+        CameraBinding {
+            viewport: viewport.rect.uv_rect(self.width, self.height),
+            ..data
+        }
+    }
+
+    /// The viewports of the layout, the first of which the controls move.
+    pub fn viewports(&self) -> Vec<Viewport> {
+        self.layout.viewports(self.width, self.height)
+    }
+
     pub fn resize_viewport(&mut self, width: u32, height: u32, queue: &wgpu::Queue) {
-        self.camera.resize_viewport(width, height, queue);
+        self.width = width;
+        self.height = height;
+        self.resize_camera(queue);
+    }
+
+    /// Splits the framebuffer into the viewports of `layout`.
+    pub fn set_layout(&mut self, layout: Layout, queue: &wgpu::Queue) {
+        self.layout = layout;
+        self.resize_camera(queue);
     }
 
-    pub fn rotate_camera(&mut self, delta: glam::Vec2, queue: &wgpu::Queue) {
-        self.camera.rotate(delta, queue);
+    pub fn set_eye_separation(&mut self, eye_separation: f32, queue: &wgpu::Queue) {
+        self.eye_separation = eye_separation;
+        self.update_camera(queue);
+    }
+
+    /// Fits the orbit camera to the first viewport, which it is seen in.
+    fn resize_camera(&mut self, queue: &wgpu::Queue) {
+        let rect = self.viewports()[0].rect;
+        self.camera.resize_viewport(rect.width, rect.height);
+        self.update_camera(queue);
+    }
+
+    /// Switches to the orbit camera and starts turning it to `view`.
+    pub fn set_view(&mut self, view: View, queue: &wgpu::Queue) {
+        if self.active_camera != ActiveCamera::Orbit {
+            self.set_camera(None, queue);
+        }
+        self.camera.set_view(view);
+    }
+
+    /// Toggles the orbit camera between perspective and orthographic.
+    pub fn toggle_projection(&mut self, queue: &wgpu::Queue) {
+        self.active_camera = ActiveCamera::Orbit;
+        self.camera.toggle_projection();
+        self.update_camera(queue);
+    }
+
+    /// Switches how the orbit camera turns, see [`ArcCamera::set_orbit_mode`].
+    pub fn set_orbit_mode(&mut self, orbit_mode: OrbitMode, queue: &wgpu::Queue) {
+        self.camera.set_orbit_mode(orbit_mode);
+        if self.active_camera == ActiveCamera::Orbit {
+            self.update_camera(queue);
+        }
+    }
+
+    /// Advances camera animations, see [`ArcCamera::update`].
+    pub fn update(&mut self, delta_time: f32, queue: &wgpu::Queue) -> bool {
+        let animating = self.camera.update(delta_time);
+        if self.active_camera == ActiveCamera::Orbit {
+            self.update_camera(queue);
+        }
+        animating
     }
 
     pub fn zoom_camera(&mut self, delta: f32, queue: &wgpu::Queue) {
-        self.camera.zoom(delta, queue);
+        if self.active_camera == ActiveCamera::Orbit {
+            self.camera.zoom(delta);
+            self.update_camera(queue);
+        }
+    }
+
+    /// Moves the orbit or fly camera by a movement of the controls. glTF
+    /// cameras stay where they are.
+    pub fn move_camera(&mut self, motion: &CameraMotion, queue: &wgpu::Queue) {
+        match self.active_camera {
+            ActiveCamera::Orbit => self.camera.apply_motion(motion),
+            ActiveCamera::Fly => self.fly_camera.apply_motion(motion),
+            ActiveCamera::Node(_) => return,
+        }
+        self.update_camera(queue);
     }
 
-    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        render_pass.set_bind_group(
-            Scene::BIND_GROUP_INDEX,
-            &self.camera.uniforms.bind_group,
-            &[],
-        );
+    /// Whether any model has transmissive materials.
+    pub fn has_transmission(&self) -> bool {
+        self.models
+            .iter()
+            .any(|model| model.materials.iter().any(|material| material.transmissive))
+    }
+
+    pub fn render<'a>(
+        &'a self,
+        pipelines: &'a Pipelines,
+        default_material: &'a Material,
+        phase: RenderPhase,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        for (viewport, uniforms) in self.viewports().iter().zip(&self.uniforms) {
+            let rect = viewport.rect;
+            if rect.is_empty() {
+                continue;
+            }
+
+            render_pass.set_viewport(
+                rect.x as f32,
+                rect.y as f32,
+                rect.width as f32,
+                rect.height as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_scissor_rect(rect.x, rect.y, rect.width, rect.height);
+            render_pass.set_bind_group(Scene::BIND_GROUP_INDEX, &uniforms.bind_group, &[]);
 
-        for model in &self.models {
-            model.render(render_pass);
+            for model in &self.models {
+                model.render(pipelines, default_material, phase, render_pass);
+            }
         }
     }
 }
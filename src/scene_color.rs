@@ -0,0 +1,106 @@
+/// The opaque scene rendered on its own, sampled by transmissive materials to
+/// show what is behind them.
+#[derive(Debug)]
+pub struct SceneColor {
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+    /// Bound while the scene color itself is the render target
+    pub placeholder_bind_group: wgpu::BindGroup,
+}
+
+impl SceneColor {
+    pub const BIND_GROUP_INDEX: u32 = 3;
+
+    pub const BIND_GROUP_LAYOUT_DESCRIPTOR: wgpu::BindGroupLayoutDescriptor<'static> =
+        wgpu::BindGroupLayoutDescriptor {
+            label: Some("Scene Color Bind Group Layout"),
+            entries: &[
+                // scene color texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // scene color sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        };
+
+    /// Creates a render target the size of the surface described by `config`.
+    pub fn new(
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let view = Self::create_texture(config.width, config.height, device)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let placeholder_view =
+            Self::create_texture(1, 1, device).create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group = Self::create_bind_group(&view, &sampler, device, layout);
+        let placeholder_bind_group =
+            Self::create_bind_group(&placeholder_view, &sampler, device, layout);
+
+        Self {
+            view,
+            bind_group,
+            placeholder_bind_group,
+        }
+    }
+
+    fn create_texture(width: u32, height: u32, device: &wgpu::Device) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Scene Color Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Same format as the swapchain view the pipelines render to
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn create_bind_group(
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Scene Color Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+}
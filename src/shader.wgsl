@@ -3,6 +3,16 @@ struct VertexIn {
     @location(1) tex_coord: vec2<f32>,
     @location(2) normal: vec3<f32>,
     @location(3) tangent: vec4<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) tex_coord_1: vec2<f32>,
+}
+
+// Columns of the instance transform
+struct InstanceIn {
+    @location(6) transform_0: vec4<f32>,
+    @location(7) transform_1: vec4<f32>,
+    @location(8) transform_2: vec4<f32>,
+    @location(9) transform_3: vec4<f32>,
 }
 
 struct FragmentIn {
@@ -11,11 +21,16 @@ struct FragmentIn {
     @location(1) world_normal: vec3<f32>,
     @location(2) world_tangent: vec3<f32>,
     @location(3) world_bitangent: vec3<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) tex_coord_1: vec2<f32>,
+    @location(6) view_position: vec3<f32>,
 }
 
 struct CameraBinding {
     view_matrix: mat4x4<f32>,
     projection_matrix: mat4x4<f32>,
+    // Offset and size of the viewport in texture coordinates of the framebuffer
+    viewport: vec4<f32>,
 }
 
 struct ModelBinding {
@@ -29,13 +44,21 @@ var<uniform> camera: CameraBinding;
 var<uniform> model: ModelBinding;
 
 @vertex
-fn vertex_main(vertex_in: VertexIn) -> FragmentIn {
-    let position = camera.projection_matrix
-        * camera.view_matrix
-        * model.model_matrix
+fn vertex_main(vertex_in: VertexIn, instance_in: InstanceIn) -> FragmentIn {
+    let instance_matrix = mat4x4<f32>(
+        instance_in.transform_0,
+        instance_in.transform_1,
+        instance_in.transform_2,
+        instance_in.transform_3,
+    );
+    let model_matrix = model.model_matrix * instance_matrix;
+
+    let view_position = camera.view_matrix
+        * model_matrix
         * vec4<f32>(vertex_in.position, 1.0);
+    let position = camera.projection_matrix * view_position;
 
-    let normal_matrix = camera.view_matrix * model.model_matrix;
+    let normal_matrix = camera.view_matrix * model_matrix;
 
     let normal = vec4<f32>(vertex_in.normal, 0.0);
     let world_normal = normal_matrix * normal;
@@ -44,13 +67,45 @@ fn vertex_main(vertex_in: VertexIn) -> FragmentIn {
     let bitangent = vec4<f32>(cross(vertex_in.normal, vertex_in.tangent.xyz) * vertex_in.tangent.w, 0.0);
     let world_bitangent = normal_matrix * bitangent;
 
-    return FragmentIn(position, vertex_in.tex_coord, world_normal.xyz, world_tangent.xyz, world_bitangent.xyz);
+    return FragmentIn(position, vertex_in.tex_coord, world_normal.xyz, world_tangent.xyz, world_bitangent.xyz, vertex_in.color, vertex_in.tex_coord_1, view_position.xyz);
 }
 
 struct MaterialFactorsBinding {
     base_color_factor: vec4<f32>,
     metallic_factor: f32,
     roughness_factor: f32,
+    ior: f32,
+    specular_factor: f32,
+    emissive_factor: vec3<f32>,
+    emissive_strength: f32,
+    specular_color_factor: vec3<f32>,
+    clearcoat_factor: f32,
+    attenuation_color: vec3<f32>,
+    // Zero if the attenuation distance is infinite
+    attenuation_distance: f32,
+    sheen_color_factor: vec3<f32>,
+    sheen_roughness_factor: f32,
+    clearcoat_roughness_factor: f32,
+    transmission_factor: f32,
+    thickness_factor: f32,
+    base_color_texture: TextureInfoBinding,
+    normal_texture: TextureInfoBinding,
+    metallic_roughness_texture: TextureInfoBinding,
+    emissive_texture: TextureInfoBinding,
+    clearcoat_texture: TextureInfoBinding,
+    clearcoat_roughness_texture: TextureInfoBinding,
+    clearcoat_normal_texture: TextureInfoBinding,
+    sheen_color_texture: TextureInfoBinding,
+    sheen_roughness_texture: TextureInfoBinding,
+    transmission_texture: TextureInfoBinding,
+    thickness_texture: TextureInfoBinding,
+    specular_texture: TextureInfoBinding,
+    specular_color_texture: TextureInfoBinding,
+}
+
+struct TextureInfoBinding {
+    uv_transform: mat3x3<f32>,
+    tex_coord: u32,
 }
 
 @group(2) @binding(0)
@@ -67,28 +122,208 @@ var normal_sampler: sampler;
 var metallic_roughness_texture: texture_2d<f32>;
 @group(2) @binding(6)
 var metallic_roughness_sampler: sampler;
+@group(2) @binding(7)
+var emissive_texture: texture_2d<f32>;
+@group(2) @binding(8)
+var emissive_sampler: sampler;
+@group(2) @binding(9)
+var clearcoat_texture: texture_2d<f32>;
+@group(2) @binding(10)
+var clearcoat_sampler: sampler;
+@group(2) @binding(11)
+var clearcoat_roughness_texture: texture_2d<f32>;
+@group(2) @binding(12)
+var clearcoat_roughness_sampler: sampler;
+@group(2) @binding(13)
+var clearcoat_normal_texture: texture_2d<f32>;
+@group(2) @binding(14)
+var clearcoat_normal_sampler: sampler;
+@group(2) @binding(15)
+var sheen_color_texture: texture_2d<f32>;
+@group(2) @binding(16)
+var sheen_color_sampler: sampler;
+@group(2) @binding(17)
+var sheen_roughness_texture: texture_2d<f32>;
+@group(2) @binding(18)
+var sheen_roughness_sampler: sampler;
+@group(2) @binding(19)
+var transmission_texture: texture_2d<f32>;
+@group(2) @binding(20)
+var transmission_sampler: sampler;
+@group(2) @binding(21)
+var thickness_texture: texture_2d<f32>;
+@group(2) @binding(22)
+var thickness_sampler: sampler;
+@group(2) @binding(23)
+var specular_texture: texture_2d<f32>;
+@group(2) @binding(24)
+var specular_sampler: sampler;
+@group(2) @binding(25)
+var specular_color_texture: texture_2d<f32>;
+@group(2) @binding(26)
+var specular_color_sampler: sampler;
+
+// Copy of the opaque scene, seen through transmissive materials
+@group(3) @binding(0)
+var scene_color_texture: texture_2d<f32>;
+@group(3) @binding(1)
+var scene_color_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+
+// Only TEXCOORD_0 and TEXCOORD_1 are supported, other sets fall back to TEXCOORD_0
+fn texture_uv(fragment_in: FragmentIn, texture_info: TextureInfoBinding) -> vec2<f32> {
+    let uv = select(fragment_in.tex_coord, fragment_in.tex_coord_1, texture_info.tex_coord == 1u);
+    return (texture_info.uv_transform * vec3<f32>(uv, 1.0)).xy;
+}
+
+// As per the spec, color is multiplied, in linear space, with the base color factor and the vertex color
+fn base_color(fragment_in: FragmentIn) -> vec4<f32> {
+    return factors.base_color_factor
+        * textureSample(base_color_texture, base_color_sampler, texture_uv(fragment_in, factors.base_color_texture))
+        * fragment_in.color;
+}
+
+fn tangent_space_normal(fragment_in: FragmentIn, texel: vec3<f32>) -> vec3<f32> {
+    let normal = texel * 2.0 - 1.0;
+    return normalize(fragment_in.world_tangent * normal.x + fragment_in.world_bitangent * normal.y + fragment_in.world_normal * normal.z);
+}
+
+fn fresnel_schlick(f0: vec3<f32>, f90: vec3<f32>, v_dot_h: f32) -> vec3<f32> {
+    return f0 + (f90 - f0) * pow(1.0 - v_dot_h, 5.0);
+}
+
+// GGX / Trowbridge-Reitz normal distribution
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let f = n_dot_h * n_dot_h * (alpha_squared - 1.0) + 1.0;
+    return alpha_squared / (PI * f * f);
+}
+
+// Height-correlated Smith visibility, including the BRDF denominator
+fn visibility_ggx(n_dot_l: f32, n_dot_v: f32, alpha: f32) -> f32 {
+    let alpha_squared = alpha * alpha;
+    let ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha_squared) + alpha_squared);
+    let ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha_squared) + alpha_squared);
+    let ggx = ggx_v + ggx_l;
+    return select(0.0, 0.5 / ggx, ggx > 0.0);
+}
+
+fn specular_brdf(normal: vec3<f32>, view: vec3<f32>, light: vec3<f32>, roughness: f32) -> f32 {
+    let half_vector = normalize(light + view);
+    let alpha = max(roughness * roughness, 0.002);
+    let n_dot_l = clamp(dot(normal, light), 0.0, 1.0);
+    let n_dot_v = clamp(dot(normal, view), 0.0, 1.0);
+    let n_dot_h = clamp(dot(normal, half_vector), 0.0, 1.0);
+    return distribution_ggx(n_dot_h, alpha) * visibility_ggx(n_dot_l, n_dot_v, alpha);
+}
+
+// Charlie sheen distribution and Neubelt visibility, as suggested by KHR_materials_sheen
+fn sheen_brdf(normal: vec3<f32>, view: vec3<f32>, light: vec3<f32>, roughness: f32) -> f32 {
+    let half_vector = normalize(light + view);
+    let alpha = max(roughness * roughness, 0.0001);
+    let n_dot_l = clamp(dot(normal, light), 0.0, 1.0);
+    let n_dot_v = clamp(dot(normal, view), 0.0, 1.0);
+    let n_dot_h = clamp(dot(normal, half_vector), 0.0, 1.0);
+    let sin_squared = 1.0 - n_dot_h * n_dot_h;
+    let distribution = (2.0 + 1.0 / alpha) * pow(sin_squared, 0.5 / alpha) / (2.0 * PI);
+    let visibility = 1.0 / max(4.0 * (n_dot_l + n_dot_v - n_dot_l * n_dot_v), 0.0001);
+    return distribution * visibility;
+}
+
+// Samples the opaque scene behind the surface, refracted through a volume of the given thickness
+fn transmitted_light(fragment_in: FragmentIn, normal: vec3<f32>, view: vec3<f32>, thickness: f32) -> vec3<f32> {
+    let refracted = refract(-view, normal, 1.0 / factors.ior);
+    let exit_position = fragment_in.view_position + refracted * thickness;
+    let clip_position = camera.projection_matrix * vec4<f32>(exit_position, 1.0);
+    let ndc = clip_position.xy / clip_position.w;
+    let uv = camera.viewport.xy + vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5) * camera.viewport.zw;
+    var light = textureSample(scene_color_texture, scene_color_sampler, uv).rgb;
+
+    // Beer-Lambert absorption, as per KHR_materials_volume
+    if factors.attenuation_distance > 0.0 {
+        let attenuation = log(max(factors.attenuation_color, vec3<f32>(0.0001))) / factors.attenuation_distance;
+        light = light * exp(attenuation * thickness);
+    }
+    return light;
+}
 
 @fragment
 fn fragment_main(fragment_in: FragmentIn) -> @location(0) vec4<f32> {
     // The color(s) returned from a fragment function are assumed to be in RGBA order,
     // regardless of the pixel format of the render target.
 
-    // As per the spec, color is multiplied, in linear space, with the base color factor
-    let base_color = factors.base_color_factor * textureSample(base_color_texture, base_color_sampler, fragment_in.tex_coord);
+    let base_color = base_color(fragment_in);
 
-    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, fragment_in.tex_coord);
+    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, texture_uv(fragment_in, factors.metallic_roughness_texture));
     let metallic = factors.metallic_factor * metallic_roughness.b;
     let roughness = factors.roughness_factor * metallic_roughness.g;
 
-    var normal = textureSample(normal_texture, normal_sampler, fragment_in.tex_coord).xyz;
-    normal = normal * 2.0 - 1.0;
-    normal = normalize(fragment_in.world_tangent * normal.x + fragment_in.world_bitangent * normal.y + fragment_in.world_normal * normal.z);
+    let normal = tangent_space_normal(fragment_in, textureSample(normal_texture, normal_sampler, texture_uv(fragment_in, factors.normal_texture)).xyz);
 
-    let light_direction = vec3<f32>(-0.25, 0.5, -0.5);
+    let emissive = factors.emissive_factor
+        * textureSample(emissive_texture, emissive_sampler, texture_uv(fragment_in, factors.emissive_texture)).rgb
+        * factors.emissive_strength;
+
+    let specular = factors.specular_factor
+        * textureSample(specular_texture, specular_sampler, texture_uv(fragment_in, factors.specular_texture)).a;
+    let specular_color = factors.specular_color_factor
+        * textureSample(specular_color_texture, specular_color_sampler, texture_uv(fragment_in, factors.specular_color_texture)).rgb;
+
+    let clearcoat = factors.clearcoat_factor
+        * textureSample(clearcoat_texture, clearcoat_sampler, texture_uv(fragment_in, factors.clearcoat_texture)).r;
+    let clearcoat_roughness = factors.clearcoat_roughness_factor
+        * textureSample(clearcoat_roughness_texture, clearcoat_roughness_sampler, texture_uv(fragment_in, factors.clearcoat_roughness_texture)).g;
+    let clearcoat_normal = tangent_space_normal(fragment_in, textureSample(clearcoat_normal_texture, clearcoat_normal_sampler, texture_uv(fragment_in, factors.clearcoat_normal_texture)).xyz);
+
+    let sheen_color = factors.sheen_color_factor
+        * textureSample(sheen_color_texture, sheen_color_sampler, texture_uv(fragment_in, factors.sheen_color_texture)).rgb;
+    let sheen_roughness = factors.sheen_roughness_factor
+        * textureSample(sheen_roughness_texture, sheen_roughness_sampler, texture_uv(fragment_in, factors.sheen_roughness_texture)).a;
+
+    let transmission = factors.transmission_factor
+        * textureSample(transmission_texture, transmission_sampler, texture_uv(fragment_in, factors.transmission_texture)).r;
+    let thickness = factors.thickness_factor
+        * textureSample(thickness_texture, thickness_sampler, texture_uv(fragment_in, factors.thickness_texture)).g;
 
+    // Shading happens in view space, with a light that follows the camera
+    let light_direction = vec3<f32>(-0.25, 0.5, -0.5);
     let light = normalize(light_direction);
+    let view = normalize(-fragment_in.view_position);
+    let ambient = 0.1;
+
     let normal_dot_light = max(dot(normal, light), 0.0);
-    let surface_color = base_color.rgb * (0.1 + normal_dot_light);
+    let view_dot_half = clamp(dot(view, normalize(light + view)), 0.0, 1.0);
+
+    // KHR_materials_ior and KHR_materials_specular tint the dielectric reflectance
+    let dielectric_f0 = min(pow((factors.ior - 1.0) / (factors.ior + 1.0), 2.0) * specular_color, vec3<f32>(1.0)) * specular;
+    let f0 = mix(dielectric_f0, base_color.rgb, metallic);
+    let f90 = vec3<f32>(mix(specular, 1.0, metallic));
+    let fresnel = fresnel_schlick(f0, f90, view_dot_half);
+
+    // KHR_materials_transmission replaces the diffuse lobe with the scene behind the surface
+    // Sampled regardless of the transmission, as texture sampling requires uniform control flow
+    let transmitted = base_color.rgb * transmitted_light(fragment_in, normal, view, thickness);
+    let diffuse = mix(base_color.rgb * (ambient + normal_dot_light), transmitted, transmission);
+
+    var color = (1.0 - fresnel) * (1.0 - metallic) * diffuse
+        + fresnel * (ambient * base_color.rgb * metallic + PI * specular_brdf(normal, view, light, roughness) * normal_dot_light);
 
-    return vec4(surface_color, base_color.a);
+    // KHR_materials_sheen sits on top of the base, which loses the energy the sheen reflects
+    let sheen_scaling = 1.0 - 0.157 * max(sheen_color.r, max(sheen_color.g, sheen_color.b));
+    color = color * sheen_scaling + sheen_color * PI * sheen_brdf(normal, view, light, sheen_roughness) * normal_dot_light;
+
+    // KHR_materials_clearcoat layers a dielectric coat with an IOR of 1.5 on top of everything
+    let clearcoat_dot_light = max(dot(clearcoat_normal, light), 0.0);
+    let clearcoat_fresnel = fresnel_schlick(vec3<f32>(0.04), vec3<f32>(1.0), clamp(dot(clearcoat_normal, view), 0.0, 1.0)) * clearcoat;
+    color = color * (1.0 - clearcoat_fresnel)
+        + clearcoat_fresnel * PI * specular_brdf(clearcoat_normal, view, light, clearcoat_roughness) * clearcoat_dot_light;
+
+    return vec4(color + emissive, base_color.a);
+}
+
+// KHR_materials_unlit
+@fragment
+fn fragment_unlit(fragment_in: FragmentIn) -> @location(0) vec4<f32> {
+    return base_color(fragment_in);
 }
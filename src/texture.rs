@@ -11,6 +11,7 @@ pub struct Texture {
 static DEFAULT_BASE_COLOR_TEXTURE: OnceLock<Texture> = OnceLock::new();
 static DEFAULT_NORMAL_TEXTURE: OnceLock<Texture> = OnceLock::new();
 static DEFAULT_METALLIC_ROUGHNESS_TEXTURE: OnceLock<Texture> = OnceLock::new();
+static DEFAULT_WHITE_TEXTURE: OnceLock<Texture> = OnceLock::new();
 
 impl fmt::Debug for Texture {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -125,6 +126,18 @@ impl Texture {
         })
     }
 
+    /// Stands in for textures whose texels are multiplied with a factor.
+    pub fn default_white_texture<'a>(device: &wgpu::Device, queue: &wgpu::Queue) -> &'a Texture {
+        DEFAULT_WHITE_TEXTURE.get_or_init(|| {
+            Self::create_solid_color_texture(
+                Some("default_white".to_string()),
+                [0xff, 0xff, 0xff, 0xff],
+                device,
+                queue,
+            )
+        })
+    }
+
     fn create_solid_color_texture(
         name: Option<String>,
         color: [u8; 4],
@@ -0,0 +1,166 @@
+use anyhow::anyhow;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::scene::ActiveCamera;
+use crate::{Renderer, Result};
+
+/// Animated image formats a turntable can be encoded as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "apng",
+        }
+    }
+}
+
+impl std::str::FromStr for AnimationFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "gif" => Ok(AnimationFormat::Gif),
+            "apng" => Ok(AnimationFormat::Apng),
+            _ => Err(anyhow!(
+                "Unknown animation format {s:?}, expected gif or apng"
+            )),
+        }
+    }
+}
+
+/// A full turn of the orbit camera around the models.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Turntable {
+    /// Frames rendered over the turn
+    pub frames: u32,
+    /// Frames per second of the animation
+    pub frame_rate: u32,
+    pub format: AnimationFormat,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            frames: 120,
+            frame_rate: 30,
+            format: AnimationFormat::Gif,
+        }
+    }
+}
+
+impl Turntable {
+    /// APNG frame delays are fractions with a 16-bit denominator
+    pub const MAX_FRAME_RATE: u32 = u16::MAX as u32;
+
+    /// Quantizing colors at the slowest speed takes seconds a frame
+    const GIF_SPEED: i32 = 10;
+
+    /// Turns the orbit camera once around its target, capturing a frame at
+    /// each step. The turn starts from the camera's current pose, as framed
+    /// on load or restored from a bookmark, and keeps its elevation
+    /// throughout. The model itself stays still, glTF animations included.
+    pub async fn render(&self, renderer: &mut Renderer) -> Result<Vec<RgbaImage>> {
+        renderer.scene.active_camera = ActiveCamera::Orbit;
+        let start_yaw = renderer.scene.camera.yaw;
+
+        let mut images = Vec::with_capacity(self.frames as usize);
+        for frame in 0..self.frames {
+            let turn = frame as f32 / self.frames as f32;
+            renderer.scene.camera.yaw = start_yaw + std::f32::consts::TAU * turn;
+            renderer.scene.update_camera(&renderer.queue);
+
+            images.push(renderer.capture().await?);
+        }
+
+        // Leave the camera where the turn started
+        renderer.scene.camera.yaw = start_yaw;
+        renderer.scene.update_camera(&renderer.queue);
+
+        Ok(images)
+    }
+
+    /// Encodes `images` as an animation looping forever.
+    pub fn encode(&self, images: &[RgbaImage]) -> Result<Vec<u8>> {
+        if images.is_empty() {
+            return Err(anyhow!("No frames to encode"));
+        }
+        if !(1..=Self::MAX_FRAME_RATE).contains(&self.frame_rate) {
+            return Err(anyhow!(
+                "Frame rate {} is out of range, expected 1 to {}",
+                self.frame_rate,
+                Self::MAX_FRAME_RATE
+            ));
+        }
+
+        match self.format {
+            AnimationFormat::Gif => self.encode_gif(images),
+            AnimationFormat::Apng => self.encode_apng(images),
+        }
+    }
+
+    fn encode_gif(&self, images: &[RgbaImage]) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut bytes, Self::GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite)?;
+
+            let delay = Delay::from_numer_denom_ms(1000, self.frame_rate);
+            encoder.encode_frames(
+                images
+                    .iter()
+                    .map(|image| Frame::from_parts(image.clone(), 0, 0, delay)),
+            )?;
+        }
+        Ok(bytes)
+    }
+
+    fn encode_apng(&self, images: &[RgbaImage]) -> Result<Vec<u8>> {
+        let (width, height) = match images.first() {
+            Some(image) => image.dimensions(),
+            None => return Err(anyhow!("No frames to encode")),
+        };
+
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, width, height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(images.len() as u32, 0)?;
+            encoder.set_frame_delay(1, self.frame_rate.try_into()?)?;
+
+            let mut writer = encoder.write_header()?;
+            for image in images {
+                writer.write_image_data(image.as_raw())?;
+            }
+            writer.finish()?;
+        }
+        Ok(bytes)
+    }
+
+    /// Renders the turntable and writes it into `directory`, as
+    /// `frame_0001.png` onwards plus `turntable.gif` or `turntable.apng`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn export(&self, renderer: &mut Renderer, directory: &std::path::Path) -> Result<()> {
+        let images = self.render(renderer).await?;
+
+        std::fs::create_dir_all(directory)?;
+        for (index, image) in images.iter().enumerate() {
+            image.save(directory.join(format!("frame_{:04}.png", index + 1)))?;
+        }
+
+        let animation = directory
+            .join("turntable")
+            .with_extension(self.format.extension());
+        std::fs::write(animation, self.encode(&images)?)?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,3 @@
-use std::mem;
-
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 
@@ -43,65 +41,19 @@ impl<T: bytemuck::NoUninit> Uniforms<T> {
     }
 }
 
-#[derive(Debug)]
-
-pub struct UniformsArray<T: bytemuck::NoUninit> {
-    pub buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
-    alignment: wgpu::BufferAddress,
-    _marker: std::marker::PhantomData<T>,
-}
-
-impl<T: bytemuck::NoUninit> UniformsArray<T> {
-    pub fn new(len: usize, device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
-        let element_size = mem::size_of::<T>() as wgpu::BufferAddress;
-        let alignment = wgpu::util::align_to(
-            element_size,
-            device.limits().min_uniform_buffer_offset_alignment as u64,
-        );
-
-        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("UniformsArrayBuffer"),
-            size: alignment * len as u64,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("UniformsArrayBindGroup"),
-            layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                    buffer: &buffer,
-                    offset: 0,
-                    size: wgpu::BufferSize::new(element_size),
-                }),
-            }],
-        });
-
-        Self {
-            buffer,
-            bind_group,
-            alignment,
-            _marker: std::marker::PhantomData,
-        }
-    }
-
-    pub fn update(&self, data: T, index: usize, queue: &wgpu::Queue) {
-        let offset = index as u64 * self.alignment;
-        queue.write_buffer(&self.buffer, offset, bytemuck::bytes_of(&data));
-    }
-
-    pub fn offset(&self, index: usize) -> wgpu::BufferAddress {
-        index as u64 * self.alignment
-    }
-}
-
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct CameraBinding {
     pub view_matrix: glam::Mat4,
     pub projection_matrix: glam::Mat4,
+    /// Offset and size of the viewport in texture coordinates of the
+    /// framebuffer, for sampling the scene color behind transmissive
+    /// surfaces
+    pub viewport: glam::Vec4,
+}
+
+impl CameraBinding {
+    pub const FULL_VIEWPORT: glam::Vec4 = glam::Vec4::new(0.0, 0.0, 1.0, 1.0);
 }
 
 #[repr(C)]
@@ -126,25 +78,76 @@ impl ModelBinding {
     }
 }
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct EntityBinding {
-    pub transform: glam::Mat4,
-}
-
 #[repr(C, align(16))]
 #[derive(Clone, Copy, Debug)]
 pub struct MaterialFactorsBinding {
     pub base_color_factor: [f32; 4],
     pub metallic_factor: f32,
     pub roughness_factor: f32,
+    pub ior: f32,
+    pub specular_factor: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_strength: f32,
+    pub specular_color_factor: [f32; 3],
+    pub clearcoat_factor: f32,
+    pub attenuation_color: [f32; 3],
+    /// Zero if the attenuation distance is infinite
+    pub attenuation_distance: f32,
+    pub sheen_color_factor: [f32; 3],
+    pub sheen_roughness_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+    pub transmission_factor: f32,
+    pub thickness_factor: f32,
+    /// Aligns the texture infos to 16 bytes, as WGSL does
+    pub _padding: u32,
+    pub base_color_texture: TextureInfoBinding,
+    pub normal_texture: TextureInfoBinding,
+    pub metallic_roughness_texture: TextureInfoBinding,
+    pub emissive_texture: TextureInfoBinding,
+    pub clearcoat_texture: TextureInfoBinding,
+    pub clearcoat_roughness_texture: TextureInfoBinding,
+    pub clearcoat_normal_texture: TextureInfoBinding,
+    pub sheen_color_texture: TextureInfoBinding,
+    pub sheen_roughness_texture: TextureInfoBinding,
+    pub transmission_texture: TextureInfoBinding,
+    pub thickness_texture: TextureInfoBinding,
+    pub specular_texture: TextureInfoBinding,
+    pub specular_color_texture: TextureInfoBinding,
+}
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug)]
+pub struct TextureInfoBinding {
+    /// Columns of the `mat3x3<f32>` UV transform, padded to `vec4`s
+    pub uv_transform: [[f32; 4]; 3],
+    pub tex_coord: u32,
+    /// Rounds the size up to 16 bytes, as WGSL does
+    pub _padding: [u32; 3],
+}
+
+impl Default for TextureInfoBinding {
+    fn default() -> Self {
+        Self::new(0, glam::Mat3::IDENTITY)
+    }
+}
+
+impl TextureInfoBinding {
+    pub fn new(tex_coord: u32, uv_transform: glam::Mat3) -> Self {
+        Self {
+            uv_transform: uv_transform
+                .to_cols_array_2d()
+                .map(|[x, y, z]| [x, y, z, 0.0]),
+            tex_coord,
+            _padding: [0; 3],
+        }
+    }
 }
 
 unsafe impl Pod for CameraBinding {}
 unsafe impl Zeroable for CameraBinding {}
 unsafe impl Pod for ModelBinding {}
 unsafe impl Zeroable for ModelBinding {}
-unsafe impl Pod for EntityBinding {}
-unsafe impl Zeroable for EntityBinding {}
 unsafe impl Pod for MaterialFactorsBinding {}
 unsafe impl Zeroable for MaterialFactorsBinding {}
+unsafe impl Pod for TextureInfoBinding {}
+unsafe impl Zeroable for TextureInfoBinding {}
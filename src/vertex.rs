@@ -1,11 +1,24 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VertexAttribute {
     Position,
     TexCoord,
     Normal,
     Tangent,
+    Color,
+    TexCoord1,
 }
 
 impl VertexAttribute {
+    pub const ALL: [VertexAttribute; 6] = [
+        VertexAttribute::Position,
+        VertexAttribute::TexCoord,
+        VertexAttribute::Normal,
+        VertexAttribute::Tangent,
+        VertexAttribute::Color,
+        VertexAttribute::TexCoord1,
+    ];
+
+    /// The format of unquantized data
     pub const fn format(&self) -> wgpu::VertexFormat {
         use wgpu::VertexFormat::*;
         match self {
@@ -13,6 +26,8 @@ impl VertexAttribute {
             VertexAttribute::TexCoord => Float32x2,
             VertexAttribute::Normal => Float32x3,
             VertexAttribute::Tangent => Float32x4,
+            VertexAttribute::Color => Float32x4,
+            VertexAttribute::TexCoord1 => Float32x2,
         }
     }
 
@@ -27,6 +42,8 @@ impl VertexAttribute {
             VertexAttribute::TexCoord => 1,
             VertexAttribute::Normal => 2,
             VertexAttribute::Tangent => 3,
+            VertexAttribute::Color => 4,
+            VertexAttribute::TexCoord1 => 5,
         }
     }
 }
@@ -35,6 +52,7 @@ type Position = [f32; 3];
 type TexCoord = [f32; 2];
 type Normal = [f32; 3];
 type Tangent = [f32; 4];
+type Color = [f32; 4];
 
 #[repr(C)]
 pub struct VertexIn {
@@ -42,30 +60,100 @@ pub struct VertexIn {
     tex_coord: TexCoord,
     normal: Normal,
     tangent: Tangent,
+    color: Color,
+    tex_coord_1: TexCoord,
 }
 
 impl VertexIn {
-    /// Use separate buffers for each attribute for now
-    pub const BUFFER_LAYOUTS: [wgpu::VertexBufferLayout<'static>; 4] = [
-        wgpu::VertexBufferLayout {
-            array_stride: VertexAttribute::Position.size(),
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![VertexAttribute::Position.location() => Float32x3],
-        },
-        wgpu::VertexBufferLayout {
-            array_stride: VertexAttribute::TexCoord.size(),
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![VertexAttribute::TexCoord.location() => Float32x2],
-        },
-        wgpu::VertexBufferLayout {
-            array_stride: VertexAttribute::Normal.size(),
+    /// Use separate buffers for each attribute for now, so that each can
+    /// have its own format.
+    pub fn buffer_layouts(
+        attributes: &[wgpu::VertexAttribute; 6],
+    ) -> [wgpu::VertexBufferLayout<'_>; 6] {
+        std::array::from_fn(|index| wgpu::VertexBufferLayout {
+            array_stride: attributes[index].format.size(),
             step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![VertexAttribute::Normal.location() => Float32x3],
-        },
-        wgpu::VertexBufferLayout {
-            array_stride: VertexAttribute::Tangent.size(),
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![VertexAttribute::Tangent.location() => Float32x4],
-        },
+            attributes: std::slice::from_ref(&attributes[index]),
+        })
+    }
+}
+
+/// Formats of the attributes that can be quantized. The others are always
+/// in the format given by `VertexAttribute::format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexFormats {
+    pub position: wgpu::VertexFormat,
+    pub tex_coord: wgpu::VertexFormat,
+    pub normal: wgpu::VertexFormat,
+    pub tex_coord_1: wgpu::VertexFormat,
+}
+
+impl Default for VertexFormats {
+    fn default() -> Self {
+        Self {
+            position: VertexAttribute::Position.format(),
+            tex_coord: VertexAttribute::TexCoord.format(),
+            normal: VertexAttribute::Normal.format(),
+            tex_coord_1: VertexAttribute::TexCoord1.format(),
+        }
+    }
+}
+
+impl VertexFormats {
+    pub fn format(&self, attribute: VertexAttribute) -> wgpu::VertexFormat {
+        match attribute {
+            VertexAttribute::Position => self.position,
+            VertexAttribute::TexCoord => self.tex_coord,
+            VertexAttribute::Normal => self.normal,
+            VertexAttribute::TexCoord1 => self.tex_coord_1,
+            _ => attribute.format(),
+        }
+    }
+
+    /// Vertex attributes ordered by shader location
+    pub fn attributes(&self) -> [wgpu::VertexAttribute; 6] {
+        VertexAttribute::ALL.map(|attribute| wgpu::VertexAttribute {
+            format: self.format(attribute),
+            offset: 0,
+            shader_location: attribute.location(),
+        })
+    }
+}
+
+/// Per-instance data, in a vertex buffer of its own that steps once per
+/// instance.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct InstanceIn {
+    /// Columns of the instance transform
+    pub transform: [[f32; 4]; 4],
+}
+
+impl InstanceIn {
+    /// The instance buffer comes after the buffers of every `VertexAttribute`
+    pub const BUFFER_INDEX: u32 = VertexAttribute::ALL.len() as u32;
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
     ];
+
+    pub const BUFFER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<InstanceIn>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &Self::ATTRIBUTES,
+    };
 }
+
+impl From<glam::Mat4> for InstanceIn {
+    fn from(transform: glam::Mat4) -> Self {
+        Self {
+            transform: transform.to_cols_array_2d(),
+        }
+    }
+}
+
+unsafe impl bytemuck::Pod for InstanceIn {}
+unsafe impl bytemuck::Zeroable for InstanceIn {}
@@ -0,0 +1,163 @@
+use anyhow::anyhow;
+
+use crate::camera::View;
+
+/// How the framebuffer is split into views of the scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// The active camera over the whole framebuffer
+    #[default]
+    Single,
+    /// The active camera at the top left, and orthographic views from the
+    /// top, front and right in the other quarters
+    Quad,
+    /// The active camera seen by a left and a right eye, side by side
+    Stereo,
+}
+
+impl Layout {
+    /// Viewports of the layout with the most of them
+    pub const MAX_VIEWPORTS: usize = 4;
+
+    /// Orthographic views of the quad layout, after the active camera's
+    const QUAD_VIEWS: [View; 3] = [View::Top, View::Front, View::Right];
+
+    /// The viewports of a framebuffer of `width` by `height` pixels.
+    pub fn viewports(self, width: u32, height: u32) -> Vec<Viewport> {
+        // The halves on the right and bottom take the odd pixel
+        let (left, top) = (width / 2, height / 2);
+        let (right, bottom) = (width - left, height - top);
+
+        match self {
+            Layout::Single => vec![Viewport {
+                rect: Rect::new(0, 0, width, height),
+                kind: ViewportKind::Active,
+            }],
+            Layout::Quad => {
+                let rects = [
+                    Rect::new(0, 0, left, top),
+                    Rect::new(left, 0, right, top),
+                    Rect::new(0, top, left, bottom),
+                    Rect::new(left, top, right, bottom),
+                ];
+                let kinds = [ViewportKind::Active]
+                    .into_iter()
+                    .chain(Self::QUAD_VIEWS.map(ViewportKind::Orthographic));
+
+                rects
+                    .into_iter()
+                    .zip(kinds)
+                    .map(|(rect, kind)| Viewport { rect, kind })
+                    .collect()
+            }
+            Layout::Stereo => vec![
+                Viewport {
+                    rect: Rect::new(0, 0, left, height),
+                    kind: ViewportKind::Eye(Eye::Left),
+                },
+                Viewport {
+                    rect: Rect::new(left, 0, right, height),
+                    kind: ViewportKind::Eye(Eye::Right),
+                },
+            ],
+        }
+    }
+
+    /// The layout after this one, for cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Layout::Single => Layout::Quad,
+            Layout::Quad => Layout::Stereo,
+            Layout::Stereo => Layout::Single,
+        }
+    }
+}
+
+impl std::str::FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "single" => Ok(Layout::Single),
+            "quad" => Ok(Layout::Quad),
+            "stereo" => Ok(Layout::Stereo),
+            _ => Err(anyhow!(
+                "Unknown layout {s:?}, expected single, quad or stereo"
+            )),
+        }
+    }
+}
+
+/// A rectangle of the framebuffer in pixels, from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Offset and size of the rectangle in texture coordinates of a
+    /// framebuffer of `width` by `height` pixels.
+    pub fn uv_rect(&self, width: u32, height: u32) -> glam::Vec4 {
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        glam::Vec4::new(
+            self.x as f32 / width,
+            self.y as f32 / height,
+            self.width as f32 / width,
+            self.height as f32 / height,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    /// Which way the eye sits from the camera along its right direction.
+    pub fn side(self) -> f32 {
+        match self {
+            Eye::Left => -1.0,
+            Eye::Right => 1.0,
+        }
+    }
+}
+
+/// What a viewport shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewportKind {
+    /// The view of the active camera
+    Active,
+    /// An orthographic view from a side, around the orbit camera's target
+    Orthographic(View),
+    /// The view of the active camera from one of a pair of eyes
+    Eye(Eye),
+}
+
+/// A part of the framebuffer showing the scene through its own camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub rect: Rect,
+    pub kind: ViewportKind,
+}
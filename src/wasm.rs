@@ -11,6 +11,7 @@ use anyhow::anyhow;
 use winit::{dpi::LogicalSize, event_loop::EventLoopBuilder, platform::web::WindowExtWebSys};
 
 use crate::app::AppEvent;
+use crate::controls::ControlSettings;
 use crate::Result;
 
 #[wasm_bindgen(start)]
@@ -22,9 +23,111 @@ pub fn wasm_start() -> Result<(), JsError> {
 pub fn load_model(path: Option<String>) -> Result<(), JsError> {
     send_event(AppEvent::LoadResourcesRequest {
         path: path.ok_or_else(|| JsError::new("No path provided"))?,
+        options: Default::default(),
     })
 }
 
+/// Switches to the material variant called `name`, or back to the default
+/// materials if `name` is `undefined`.
+#[wasm_bindgen(js_name = setVariant)]
+pub fn set_variant(name: Option<String>) -> Result<(), JsError> {
+    send_event(AppEvent::SetVariantRequest(name))
+}
+
+/// Names of the material variants of the loaded model.
+#[wasm_bindgen]
+pub fn variants() -> js_sys::Array {
+    crate::app::VARIANTS.with_borrow(|variants| {
+        variants
+            .iter()
+            .map(|variant| JsValue::from_str(variant))
+            .collect()
+    })
+}
+
+/// Views the model through the glTF camera called `name`, or with the index
+/// `name`, or through the orbit camera if `name` is `undefined`.
+#[wasm_bindgen(js_name = setCamera)]
+pub fn set_camera(name: Option<String>) -> Result<(), JsError> {
+    send_event(AppEvent::SetCameraRequest(name))
+}
+
+/// Names of the glTF cameras of the loaded model.
+#[wasm_bindgen]
+pub fn cameras() -> js_sys::Array {
+    crate::app::CAMERAS.with_borrow(|cameras| {
+        cameras
+            .iter()
+            .map(|camera| JsValue::from_str(camera))
+            .collect()
+    })
+}
+
+/// Switches the orbit camera between `"turntable"` and `"trackball"`
+/// orbiting.
+#[wasm_bindgen(js_name = setOrbitMode)]
+pub fn set_orbit_mode(mode: String) -> Result<(), JsError> {
+    let orbit_mode = mode
+        .parse()
+        .map_err(|error: anyhow::Error| JsError::new(&error.to_string()))?;
+    send_event(AppEvent::SetOrbitModeRequest(orbit_mode))
+}
+
+/// Splits the canvas into a `"single"`, `"quad"` or `"stereo"` view.
+#[wasm_bindgen(js_name = setLayout)]
+pub fn set_layout(layout: String) -> Result<(), JsError> {
+    let layout = layout
+        .parse()
+        .map_err(|error: anyhow::Error| JsError::new(&error.to_string()))?;
+    send_event(AppEvent::SetLayoutRequest(layout))
+}
+
+/// Sets the distance between the eyes of the stereo view, in the model's
+/// units.
+#[wasm_bindgen(js_name = setEyeSeparation)]
+pub fn set_eye_separation(eye_separation: f32) -> Result<(), JsError> {
+    send_event(AppEvent::SetEyeSeparationRequest(eye_separation))
+}
+
+/// The bookmarks of the loaded model, as JSON to keep for later.
+#[wasm_bindgen]
+pub fn bookmarks() -> String {
+    crate::app::BOOKMARKS.with_borrow(|bookmarks| crate::bookmark::to_json(bookmarks))
+}
+
+/// Replaces the bookmarks with the ones in `json`, as returned by
+/// `bookmarks()`.
+#[wasm_bindgen(js_name = setBookmarks)]
+pub fn set_bookmarks(json: String) -> Result<(), JsError> {
+    let bookmarks =
+        crate::bookmark::from_json(&json).map_err(|error| JsError::new(&error.to_string()))?;
+    send_event(AppEvent::SetBookmarksRequest(bookmarks))
+}
+
+/// Saves the current view as the bookmark called `name`.
+#[wasm_bindgen(js_name = saveBookmark)]
+pub fn save_bookmark(name: String) -> Result<(), JsError> {
+    send_event(AppEvent::SaveBookmarkRequest(name))
+}
+
+/// Moves the camera smoothly to the bookmark called `name`.
+#[wasm_bindgen(js_name = goToBookmark)]
+pub fn go_to_bookmark(name: String) -> Result<(), JsError> {
+    send_event(AppEvent::GoToBookmarkRequest(name))
+}
+
+/// Sets the sensitivity multipliers of the mouse controls, and the seconds
+/// the camera takes to follow them.
+#[wasm_bindgen(js_name = setControls)]
+pub fn set_controls(orbit: f32, pan: f32, zoom: f32, damping: f32) -> Result<(), JsError> {
+    send_event(AppEvent::SetControlsRequest(ControlSettings {
+        orbit_sensitivity: orbit,
+        pan_sensitivity: pan,
+        zoom_sensitivity: zoom,
+        damping,
+    }))
+}
+
 pub fn send_event(event: AppEvent) -> Result<(), JsError> {
     crate::app::EVENT_LOOP_PROXY.with_borrow(|proxy| {
         proxy
@@ -66,12 +169,21 @@ fn wasm_main() -> Result<()> {
         .and_then(|container| container.append_child(&window.canvas()).ok())
         .ok_or_else(|| anyhow!("Failed to append canvas to body"))?;
 
-    wasm_bindgen_futures::spawn_local(crate::app::run(window, event_loop, false).unwrap_or_else(
-        |err| {
+    // Right drags pan the camera, so keep the context menu from opening
+    let prevent_default = Closure::<dyn FnMut(web_sys::Event)>::new(|event: web_sys::Event| {
+        event.prevent_default();
+    });
+    window
+        .canvas()
+        .set_oncontextmenu(Some(prevent_default.as_ref().unchecked_ref()));
+    prevent_default.forget();
+
+    wasm_bindgen_futures::spawn_local(
+        crate::app::run(window, event_loop, false, Default::default()).unwrap_or_else(|err| {
             tracing::error!(?err);
             ()
-        },
-    ));
+        }),
+    );
     Ok(())
 }
 
@@ -99,8 +211,10 @@ async fn fetch_gltf<P: AsRef<path::Path>>(path: P) -> Result<gltf::Gltf, JsValue
     let response = fetch(&path).await?;
     let array_buffer = JsFuture::from(response.array_buffer()?).await?;
     let gltf_data = js_sys::Uint8Array::new(&array_buffer).to_vec();
-    let gltf =
-        gltf::Gltf::from_slice(&gltf_data).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let gltf = gltf::Gltf::from_slice_without_validation(&gltf_data)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    crate::resources::check_required_extensions(&gltf)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
     Ok(gltf)
 }
 
@@ -116,6 +230,12 @@ pub async fn import_gltf<P: AsRef<path::Path>>(
     let mut buffers = Vec::new();
 
     for buffer in document.buffers() {
+        if crate::resources::is_meshopt_fallback(&buffer) {
+            tracing::debug!("Skipping meshopt fallback buffer {}", buffer.index());
+            buffers.push(Vec::new());
+            continue;
+        }
+
         let data = match buffer.source() {
             gltf::buffer::Source::Uri(uri) => {
                 let response = if uri.starts_with("data:") {
@@ -142,7 +262,7 @@ pub async fn import_gltf<P: AsRef<path::Path>>(
     let mut images = Vec::new();
 
     for image in document.images() {
-        let image_bitmap = match image.source() {
+        let blob: web_sys::Blob = match image.source() {
             gltf::image::Source::Uri { uri, mime_type: _ } => {
                 let response = if uri.starts_with("data:") {
                     tracing::debug!("Fetching image from data URI");
@@ -153,26 +273,37 @@ pub async fn import_gltf<P: AsRef<path::Path>>(
                     fetch(uri).await
                 }?;
 
-                let blob = JsFuture::from(response.blob()?)
+                JsFuture::from(response.blob()?)
                     .await?
                     .dyn_into()
-                    .expect("Blob object");
+                    .expect("Blob object")
+            }
+            gltf::image::Source::View { view, mime_type } => {
+                tracing::debug!("Decoding image from buffer view {}", view.index());
 
-                let image_bitmap = JsFuture::from(window.create_image_bitmap_with_blob(&blob)?)
-                    .await?
-                    .dyn_into::<web_sys::ImageBitmap>()
-                    .expect("ImageBitmap object");
+                let data = buffers
+                    .get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(view.offset()..view.offset() + view.length()))
+                    .ok_or_else(|| {
+                        JsValue::from_str("Image buffer view is out of bounds of its buffer")
+                    })?;
+
+                let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
 
-                tracing::debug!(width = image_bitmap.width(), height = image_bitmap.height());
+                let mut options = web_sys::BlobPropertyBag::new();
+                options.type_(mime_type);
 
-                image_bitmap
+                web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?
             }
-            gltf::image::Source::View {
-                view: _,
-                mime_type: _,
-            } => todo!(),
         };
 
+        let image_bitmap = JsFuture::from(window.create_image_bitmap_with_blob(&blob)?)
+            .await?
+            .dyn_into::<web_sys::ImageBitmap>()
+            .expect("ImageBitmap object");
+
+        tracing::debug!(width = image_bitmap.width(), height = image_bitmap.height());
+
         images.push(image_bitmap);
     }
 